@@ -1,14 +1,12 @@
 use std::result::Result::Ok;
 use anyhow::*;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-use std::time::Instant;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::HashMap;
+use std::thread;
 
-use code_timing_macros::time_snippet;
 use const_format::concatcp;
 use advent_of_code_2024::*;
-use log::error;
+use advent_of_code_2024::locations::{self, Reconciliation};
+use advent_of_code_2024::report::{Format, Json, Table};
 
 const DAY: &str = "01";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -23,121 +21,577 @@ const TEST: &str = r#"
 3   3
 "#;
 
-fn main() -> Result<()> {
-    start_day(DAY);
+/// The left and right location lists, in input order
+type LocationLists = (Vec<usize>, Vec<usize>);
 
-    //region Part 1
-    println!("=== Part 1 ===");
+fn parse_number_pairs_file(bytes: &[u8]) -> Result<LocationLists> {
+    let mut left_values = Vec::new();
+    let mut right_values = Vec::new();
 
-    fn parse_number_pairs_file<R: BufRead>(reader: R) -> io::Result<(Vec<usize>, Vec<usize>)> {
-        let mut left_values = Vec::new();
-        let mut right_values = Vec::new();
-
-        for line in reader.lines() {
-            let line = line?;
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let mut parts = line.split_whitespace();
-            if let (Some(left_str), Some(right_str)) = (parts.next(), parts.next()) {
-                match (left_str.parse::<usize>(), right_str.parse::<usize>()) {
-                    (Ok(left_val), Ok(right_val)) => {
-                        left_values.push(left_val);
-                        right_values.push(right_val);
-                    }
-                    (Err(_), _) | (_, Err(_)) => {
-                        eprintln!("Invalid input: failed to parse '{}' or '{}' as integers.",
-                                  left_str, right_str);
-                    }
+    for line in input::lines(bytes) {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = input::fields(line);
+        if let (Some(left_str), Some(right_str)) = (parts.next(), parts.next()) {
+            match (input::parse_usize(left_str), input::parse_usize(right_str)) {
+                (Some(left_val), Some(right_val)) => {
+                    left_values.push(left_val);
+                    right_values.push(right_val);
+                }
+                (None, _) | (_, None) => {
+                    eprintln!("Invalid input: failed to parse '{}' or '{}' as integers.",
+                              String::from_utf8_lossy(left_str), String::from_utf8_lossy(right_str));
                 }
-            } else {
-                eprintln!("Could not parse input as two separate space-separated strings")
             }
+        } else {
+            eprintln!("Could not parse input as two separate space-separated strings")
         }
-
-        Ok((left_values, right_values))
     }
 
-    fn part1<R: BufRead>(reader: R) -> Result<usize> {
-        let mut result: usize = 0;
+    Ok((left_values, right_values))
+}
+
+fn part1((left_values, right_values): &LocationLists) -> Result<usize> {
+    locations::checked_total_distance(left_values.iter().copied(), right_values.iter().copied())
+}
 
-        let (mut left_values, mut right_values) = parse_number_pairs_file(reader)?;
+//region Part 1 variants
+/// Alternative implementations of part 1 for big boy inputs, selected with `--variant`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Part1Variant {
+    /// `part1` itself: comparison sort
+    Sort,
+    /// LSD radix sort, one pass per significant byte of the largest value
+    Radix,
+    /// Histograms of both lists walked in lockstep, without sorting at all. Only for bounded ranges.
+    Counting,
+    /// Chunks sorted on all cores, then merged
+    Parallel,
+}
 
-        left_values.sort_unstable();
-        right_values.sort_unstable();
+impl Part1Variant {
+    const ALL: [Part1Variant; 4] = [Self::Sort, Self::Radix, Self::Counting, Self::Parallel];
 
-        // Create iterators for both sets
-        let mut left_iter = left_values.iter();
-        let mut right_iter = right_values.iter();
+    fn name(self) -> &'static str {
+        match self {
+            Self::Sort => "sort",
+            Self::Radix => "radix",
+            Self::Counting => "counting",
+            Self::Parallel => "parallel",
+        }
+    }
 
-        // Initialize first elements
-        let mut left_next = left_iter.next();
-        let mut right_next = right_iter.next();
+    fn from_name(name: &str) -> Result<Self> {
+        Self::ALL.into_iter()
+            .find(|v| v.name() == name)
+            .ok_or_else(|| anyhow!("Unknown variant '{}', expected one of sort, radix, counting, parallel or all", name))
+    }
 
-        while let (Some(&left_val), Some(&right_val)) = (left_next, right_next) {
-            let mut distance: usize = 0;
-            if (left_val < right_val) {
-                distance = (right_val - left_val) as usize;
-            } else {
-                distance = (left_val - right_val) as usize;
+    fn solve(self, lists: &LocationLists) -> Result<usize> {
+        let (left_values, right_values) = lists;
+        match self {
+            Self::Sort => part1(lists),
+            Self::Radix => sorted_distance(&radix_sorted(left_values), &radix_sorted(right_values)),
+            Self::Counting => counting_distance(left_values, right_values),
+            Self::Parallel => {
+                let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+                let (left, right) = thread::scope(|s| {
+                    let left = s.spawn(|| parallel_sorted(left_values, threads));
+                    let right = parallel_sorted(right_values, threads);
+                    (left.join().expect("sorting thread panicked"), right)
+                });
+                sorted_distance(&left, &right)
             }
-            // println!("left: '{}', right: '{}', distance: '{}'.", left_val, right_val, distance);
-            result += distance;
-            left_next = left_iter.next();
-            right_next = right_iter.next();
         }
-        Ok(result)
     }
+}
+
+/// Sum of distances between two sorted lists, pair by pair
+fn sorted_distance(left_values: &[usize], right_values: &[usize]) -> Result<usize> {
+    left_values.iter().zip(right_values).try_fold(0usize, |total, (&l, &r)| {
+        total.checked_add(l.abs_diff(r)).ok_or_else(|| anyhow!("Total distance overflows at {} and {}", l, r))
+    })
+}
+
+fn radix_sorted(values: &[usize]) -> Vec<usize> {
+    let mut values = values.to_vec();
+    let mut buffer = vec![0; values.len()];
+
+    let max = values.iter().copied().max().unwrap_or(0);
+    let passes = (usize::BITS - max.leading_zeros()).div_ceil(8);
+
+    for pass in 0..passes {
+        let shift = pass * 8;
+        let mut offsets = [0usize; 257];
+        for &v in &values {
+            offsets[((v >> shift) & 0xff) + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        for &v in &values {
+            let digit = (v >> shift) & 0xff;
+            buffer[offsets[digit]] = v;
+            offsets[digit] += 1;
+        }
+        std::mem::swap(&mut values, &mut buffer);
+    }
+
+    values
+}
+
+/// The largest value range the counting variant allocates histograms for
+const MAX_COUNTING_RANGE: usize = 1 << 22;
+
+fn counting_distance(left_values: &[usize], right_values: &[usize]) -> Result<usize> {
+    let all = || left_values.iter().chain(right_values);
+    let (Some(&min), Some(&max)) = (all().min(), all().max()) else {
+        return Ok(0);
+    };
+    if max - min >= MAX_COUNTING_RANGE {
+        bail!("Value range {}..={} is too large for counting, use another variant", min, max);
+    }
+
+    let histogram = |values: &[usize]| {
+        let mut counts = vec![0usize; max - min + 1];
+        for &v in values {
+            counts[v - min] += 1;
+        }
+        counts
+    };
+    let mut left_counts = histogram(left_values);
+    let mut right_counts = histogram(right_values);
+
+    // Pair up the i-th smallest values of both lists without materializing the sorted lists
+    let mut result = 0;
+    let (mut l, mut r) = (0, 0);
+    loop {
+        while l < left_counts.len() && left_counts[l] == 0 {
+            l += 1;
+        }
+        while r < right_counts.len() && right_counts[r] == 0 {
+            r += 1;
+        }
+        if l == left_counts.len() || r == right_counts.len() {
+            return Ok(result);
+        }
+        let pairs = left_counts[l].min(right_counts[r]);
+        result = pairs.checked_mul(l.abs_diff(r))
+            .and_then(|distance| result.checked_add(distance))
+            .ok_or_else(|| anyhow!("Total distance overflows at {} and {}", min + l, min + r))?;
+        left_counts[l] -= pairs;
+        right_counts[r] -= pairs;
+    }
+}
+
+fn parallel_sorted(values: &[usize], threads: usize) -> Vec<usize> {
+    let chunk_size = values.len().div_ceil(threads).max(1);
+
+    let mut values = values.to_vec();
+    thread::scope(|s| {
+        for chunk in values.chunks_mut(chunk_size) {
+            s.spawn(|| chunk.sort_unstable());
+        }
+    });
+
+    // Merge neighbouring sorted runs until only one is left
+    let mut buffer = vec![0; values.len()];
+    let mut run = chunk_size;
+    while run < values.len() {
+        for (start, out) in (0..values.len()).step_by(2 * run).zip(buffer.chunks_mut(2 * run)) {
+            let mid = (start + run).min(values.len());
+            let end = (start + 2 * run).min(values.len());
+            merge(&values[start..mid], &values[mid..end], out);
+        }
+        std::mem::swap(&mut values, &mut buffer);
+        run *= 2;
+    }
+
+    values
+}
+
+fn merge(left: &[usize], right: &[usize], out: &mut [usize]) {
+    let (mut i, mut j) = (0, 0);
+    for slot in out.iter_mut() {
+        if j == right.len() || (i < left.len() && left[i] <= right[j]) {
+            *slot = left[i];
+            i += 1;
+        } else {
+            *slot = right[j];
+            j += 1;
+        }
+    }
+}
+//endregion
+
+fn part2((left_values, right_values): &LocationLists) -> Result<usize> {
+    locations::checked_similarity_score(left_values.iter().copied(), right_values.iter().copied())
+}
+
+//region Report
+/// What `--report` prints, to see where the answers come from without adding `println!`s
+#[derive(Debug)]
+struct PairingReport {
+    /// (left, right, distance) for the i-th smallest values of both lists
+    pairs: Vec<(usize, usize, usize)>,
+    /// Indices into `pairs` of the largest distances, largest first
+    largest: Vec<usize>,
+    /// (smallest, largest, count) of distances per power-of-two bucket
+    histogram: Vec<(usize, usize, usize)>,
+    /// (left, occurrences in the right list, contribution to part 2) per left value, in input order
+    similarity: Vec<(usize, usize, usize)>,
+}
+
+/// Bucket 0 holds distance 0, bucket `b` holds distances `2^(b-1)..2^b`
+fn distance_bucket(distance: usize) -> usize {
+    (usize::BITS - distance.leading_zeros()) as usize
+}
+
+fn bucket_bounds(bucket: usize) -> (usize, usize) {
+    match bucket {
+        0 => (0, 0),
+        b => (1 << (b - 1), ((1u128 << b) - 1) as usize),
+    }
+}
+
+fn pairing_report((left_values, right_values): &LocationLists, top_k: usize) -> Result<PairingReport> {
+    let mut left_sorted = left_values.clone();
+    let mut right_sorted = right_values.clone();
+    left_sorted.sort_unstable();
+    right_sorted.sort_unstable();
+
+    let pairs: Vec<(usize, usize, usize)> = left_sorted.iter().zip(&right_sorted)
+        .map(|(&l, &r)| (l, r, l.abs_diff(r)))
+        .collect();
+
+    let mut largest: Vec<usize> = (0..pairs.len()).collect();
+    // Stable, so equal distances keep the pairing order
+    largest.sort_by(|&a, &b| pairs[b].2.cmp(&pairs[a].2));
+    largest.truncate(top_k);
+
+    let mut bucket_counts = Vec::new();
+    for &(_, _, distance) in &pairs {
+        let bucket = distance_bucket(distance);
+        if bucket_counts.len() <= bucket {
+            bucket_counts.resize(bucket + 1, 0);
+        }
+        bucket_counts[bucket] += 1;
+    }
+    let histogram = bucket_counts.into_iter().enumerate()
+        .map(|(bucket, count)| {
+            let (low, high) = bucket_bounds(bucket);
+            (low, high, count)
+        })
+        .collect();
+
+    let mut occurrence_counts = HashMap::new();
+    for &right_val in right_values {
+        *occurrence_counts.entry(right_val).or_insert(0usize) += 1;
+    }
+    let similarity = left_values.iter()
+        .map(|&l| {
+            let count = occurrence_counts.get(&l).copied().unwrap_or(0);
+            let contribution = l.checked_mul(count)
+                .ok_or_else(|| anyhow!("Similarity score overflows at {} appearing {} times", l, count))?;
+            Ok((l, count, contribution))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(PairingReport { pairs, largest, histogram, similarity })
+}
+
+impl PairingReport {
+    fn to_json(&self) -> Json {
+        let triples = |rows: &mut dyn Iterator<Item = &(usize, usize, usize)>, names: [&str; 3]| {
+            Json::Array(rows
+                .map(|&(a, b, c)| Json::object([
+                    (names[0], Json::number(a)),
+                    (names[1], Json::number(b)),
+                    (names[2], Json::number(c)),
+                ]))
+                .collect())
+        };
+
+        Json::object([
+            ("pairs", triples(&mut self.pairs.iter(), ["left", "right", "distance"])),
+            ("largest", triples(&mut self.largest.iter().map(|&i| &self.pairs[i]), ["left", "right", "distance"])),
+            ("histogram", triples(&mut self.histogram.iter(), ["min", "max", "count"])),
+            ("similarity", triples(&mut self.similarity.iter(), ["left", "count", "contribution"])),
+        ])
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        let mut pairs = Table::new("Sorted pairing", &["#", "left", "right", "distance"]);
+        for (i, (l, r, d)) in self.pairs.iter().enumerate() {
+            pairs.row([i, *l, *r, *d]);
+        }
+
+        let mut largest = Table::new("Largest distances", &["#", "left", "right", "distance"]);
+        for &i in &self.largest {
+            let (l, r, d) = self.pairs[i];
+            largest.row([i, l, r, d]);
+        }
+
+        let max_count = self.histogram.iter().map(|h| h.2).max().unwrap_or(0).max(1);
+        let mut histogram = Table::new("Distance histogram", &["distances", "count", ""]);
+        for &(low, high, count) in &self.histogram {
+            let range = if low == high { low.to_string() } else { format!("{}-{}", low, high) };
+            let bar = "#".repeat((count * 40).div_ceil(max_count));
+            histogram.row([range, count.to_string(), bar]);
+        }
+
+        let mut similarity = Table::new("Similarity", &["left", "count", "contribution"]);
+        for (l, count, contribution) in &self.similarity {
+            similarity.row([l, count, contribution]);
+        }
+
+        vec![pairs, largest, histogram, similarity]
+    }
+}
 
-    assert_eq!(11, part1(BufReader::new(TEST.as_bytes()))?);
+/// Columns are numbered from 1, as they appear in the input
+fn reconciliation_tables(reconciliation: &Reconciliation) -> Vec<Table> {
+    let names: Vec<String> = (1..=reconciliation.columns()).map(|c| c.to_string()).collect();
+    let headers: Vec<&str> = std::iter::once("column").chain(names.iter().map(String::as_str)).collect();
+
+    let matrix = |title: &str, values: &[Vec<usize>]| {
+        let mut table = Table::new(title, &headers);
+        for (name, row) in names.iter().zip(values) {
+            table.row(std::iter::once(name.clone()).chain(row.iter().map(usize::to_string)));
+        }
+        table
+    };
+
+    vec![
+        matrix("Total distance", &reconciliation.distances),
+        matrix("Similarity (row against column)", &reconciliation.similarities),
+    ]
+}
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file)?);
-    assert_eq!(2031679, result);
-    println!("Result = {}", result);
+fn reconciliation_json(reconciliation: &Reconciliation) -> Json {
+    let matrix = |values: &[Vec<usize>]| {
+        Json::Array(values.iter()
+            .map(|row| Json::Array(row.iter().map(|&v| Json::number(v)).collect()))
+            .collect())
+    };
+    let closest = match reconciliation.closest_pair() {
+        Some((a, b)) => Json::Array(vec![Json::number(a + 1), Json::number(b + 1)]),
+        None => Json::Null,
+    };
 
-    // let result = time_snippet!(part1(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    Json::object([
+        ("distances", matrix(&reconciliation.distances)),
+        ("similarities", matrix(&reconciliation.similarities)),
+        ("closest", closest),
+    ])
+}
+//endregion
+
+fn main() -> Result<()> {
+    start_day(DAY);
+    let mut runner = Runner::with_options(DAY, INPUT_FILE, &["--variant", "--report", "--top", "--reconcile"])?;
+
+    // `--variant all` runs every variant, which together with `bench` compares them
+    let variants = match runner.option("--variant") {
+        Some("all") => Part1Variant::ALL.to_vec(),
+        Some(name) => vec![Part1Variant::from_name(name)?],
+        None => vec![Part1Variant::Sort],
+    };
+    let report = runner.option("--report").map(Format::from_name).transpose()?;
+    let reconcile = runner.option("--reconcile").map(Format::from_name).transpose()?;
+    let top_k = match runner.option("--top") {
+        Some(k) => k.parse().with_context(|| format!("Invalid --top '{}'", k))?,
+        None => 10,
+    };
+
+    //region Parse
+    runner.section("Parse");
+
+    let parsed = runner.parse(|input| parse_number_pairs_file(input.as_bytes()));
+    //endregion
+
+    //region Part 1
+    runner.section("Part 1");
+
+    runner.example(11, || part1(&parse_number_pairs_file(TEST.as_bytes())?));
+    // Every other variant is cross-checked against the original sort on the same input, so pooled
+    // inputs without recorded answers are checked as well
+    for variant in variants {
+        match variant {
+            Part1Variant::Sort => runner.solve_expecting(&parsed, 2031679, part1),
+            _ => {
+                let label = format!("Result ({})", variant.name());
+                runner.solve_against(&parsed, &label, part1, move |lists| variant.solve(lists));
+            }
+        }
+    }
+
+    // runner.part("Result (big boy)", || part1(&parse_number_pairs_file(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
     //endregion
 
     //region Part 2
-    println!("\n=== Part 2 ===");
+    runner.section("Part 2");
 
-    fn part2<R: BufRead>(reader: R) -> Result<usize> {
-        let mut result: usize = 0;
+    runner.example(31, || part2(&parse_number_pairs_file(TEST.as_bytes())?));
+    runner.solve_expecting(&parsed, 19678534, part2);
 
-        let (mut left_values, mut right_values) = parse_number_pairs_file(reader)?;
+    // runner.part("Result (big boy)", || part2(&parse_number_pairs_file(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
+    //endregion
 
-        let mut occurrence_counts = HashMap::new();
+    //region Report
+    if let Some(format) = report {
+        runner.section("Report");
+        match (parsed.default_input(), format) {
+            (None, _) => println!("No parsed input to report on"),
+            (Some(lists), Format::Json) => println!("{}", pairing_report(lists, top_k)?.to_json()),
+            (Some(lists), Format::Table) => {
+                for table in pairing_report(lists, top_k)?.tables() {
+                    println!("{}", table);
+                }
+            }
+        }
+    }
 
-        for right_val in right_values {
-            *occurrence_counts.entry(right_val).or_insert(0) += 1;
+    // Compares every column of the input, not just the two the parts use
+    if let Some(format) = reconcile {
+        runner.section("Reconciliation");
+        // Parsed again through the runner, as the parts only keep the first two columns
+        let reconciled = runner.parse(|input| locations::reconcile(&locations::parse_columns(input.as_bytes())?));
+        match (reconciled.default_input(), format) {
+            (None, _) => println!("No parsed input to reconcile"),
+            (Some(reconciliation), Format::Json) => println!("{}", reconciliation_json(reconciliation)),
+            (Some(reconciliation), Format::Table) => {
+                for table in reconciliation_tables(reconciliation) {
+                    println!("{}", table);
+                }
+                match reconciliation.closest_pair() {
+                    Some((a, b)) => println!("Columns {} and {} agree most", a + 1, b + 1),
+                    None => println!("Fewer than two columns to compare"),
+                }
+            }
         }
+    }
+    //endregion
 
-        for left_val in left_values {
-            // println!("Finding occurrence count for '{}'.", left_val);
-            if let Some(&occurrence_count) = occurrence_counts.get(&left_val) {
-                result += left_val * occurrence_count;
+    runner.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2024::testing::XorShift;
+
+    /// Deterministic pseudo-random lists, so failures are reproducible
+    fn random_lists(len: usize, max: usize, seed: u64) -> LocationLists {
+        let mut rng = XorShift::new(seed);
+        let mut next = || rng.below(max as u64 + 1) as usize;
+        let left = (0..len).map(|_| next()).collect();
+        let right = (0..len).map(|_| next()).collect();
+        (left, right)
+    }
+
+    #[test]
+    fn test_variants_match_sort_on_example() {
+        let lists = parse_number_pairs_file(TEST.as_bytes()).unwrap();
+        for variant in Part1Variant::ALL {
+            assert_eq!(variant.solve(&lists).unwrap(), 11, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_variants_match_sort_on_random_lists() {
+        for (len, max, seed) in [(0, 10, 1), (1, 10, 2), (1000, 99999, 3), (4097, 7, 4), (3000, 1 << 40, 5)] {
+            let lists = random_lists(len, max, seed);
+            let expected = part1(&lists).unwrap();
+            for variant in [Part1Variant::Radix, Part1Variant::Parallel] {
+                assert_eq!(variant.solve(&lists).unwrap(), expected, "{:?} with len {}", variant, len);
+            }
+            if max < MAX_COUNTING_RANGE {
+                assert_eq!(Part1Variant::Counting.solve(&lists).unwrap(), expected, "Counting with len {}", len);
             }
         }
+    }
 
-        Ok(result)
+    #[test]
+    fn test_variants_report_overflow() {
+        let lists = (vec![0, 0], vec![usize::MAX, usize::MAX]);
+        for variant in [Part1Variant::Sort, Part1Variant::Radix, Part1Variant::Parallel] {
+            let err = variant.solve(&lists).unwrap_err();
+            assert!(err.to_string().contains("Total distance overflows"), "{:?}: {}", variant, err);
+        }
+        // Counting refuses the range before it could overflow
+        assert!(Part1Variant::Counting.solve(&lists).is_err());
     }
 
-    assert_eq!(31, part2(BufReader::new(TEST.as_bytes()))?);
+    #[test]
+    fn test_parallel_sorted_merges_chunks() {
+        let (values, _) = random_lists(1001, 500, 6);
+        let mut expected = values.clone();
+        expected.sort_unstable();
+        for threads in [1, 2, 3, 8, 2000] {
+            assert_eq!(parallel_sorted(&values, threads), expected, "{} threads", threads);
+        }
+    }
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part2(input_file)?);
-    assert_eq!(19678534, result);
-    println!("Result = {}", result);
+    #[test]
+    fn test_counting_rejects_large_range() {
+        let lists = (vec![0, 1 << 40], vec![5, 6]);
+        assert!(Part1Variant::Counting.solve(&lists).is_err());
+    }
 
-    // let input_file_big_boy = BufReader::new(File::open(INPUT_FILE_BIG_BOY)?);
-    // let result = time_snippet!(part2(input_file_big_boy)?);
-    // println!("Result (big boy) = {}", result);
-    //endregion
+    #[test]
+    fn test_pairing_report_on_example() {
+        let lists = parse_number_pairs_file(TEST.as_bytes()).unwrap();
+        let report = pairing_report(&lists, 2).unwrap();
+
+        assert_eq!(report.pairs, vec![(1, 3, 2), (2, 3, 1), (3, 3, 0), (3, 4, 1), (3, 5, 2), (4, 9, 5)]);
+        assert_eq!(report.pairs.iter().map(|p| p.2).sum::<usize>(), 11);
+        assert_eq!(report.largest, vec![5, 0]);
+        assert_eq!(report.histogram, vec![(0, 0, 1), (1, 1, 2), (2, 3, 2), (4, 7, 1)]);
+        assert_eq!(report.similarity, vec![(3, 3, 9), (4, 1, 4), (2, 0, 0), (1, 0, 0), (3, 3, 9), (3, 3, 9)]);
+        assert_eq!(report.similarity.iter().map(|s| s.2).sum::<usize>(), 31);
+    }
 
-    Ok(())
+    #[test]
+    fn test_pairing_report_overflow() {
+        let err = pairing_report(&(vec![usize::MAX], vec![usize::MAX, usize::MAX]), 1).unwrap_err();
+        assert!(err.to_string().contains("Similarity score overflows"));
+    }
+
+    #[test]
+    fn test_parts_are_two_column_reconciliation() {
+        let lists = random_lists(500, 1000, 11);
+        let reconciliation = locations::reconcile(&[lists.0.clone(), lists.1.clone()]).unwrap();
+        assert_eq!(part1(&lists).unwrap(), reconciliation.distances[0][1]);
+        assert_eq!(part2(&lists).unwrap(), reconciliation.similarities[0][1]);
+    }
+
+    #[test]
+    fn test_reconciliation_json() {
+        let reconciliation = locations::reconcile(&[vec![1, 2], vec![2, 2], vec![1, 3]]).unwrap();
+        assert_eq!(
+            reconciliation_json(&reconciliation).to_string(),
+            r#"{"distances":[[0,1,1],[1,0,2],[1,2,0]],"similarities":[[3,4,1],[4,8,0],[1,0,4]],"closest":[1,2]}"#,
+        );
+    }
+
+    #[test]
+    fn test_pairing_report_json() {
+        let lists = (vec![1, 5], vec![2, 5]);
+        let json = pairing_report(&lists, 1).unwrap().to_json().to_string();
+        assert_eq!(json, concat!(
+            r#"{"pairs":[{"left":1,"right":2,"distance":1},{"left":5,"right":5,"distance":0}],"#,
+            r#""largest":[{"left":1,"right":2,"distance":1}],"#,
+            r#""histogram":[{"min":0,"max":0,"count":1},{"min":1,"max":1,"count":1}],"#,
+            r#""similarity":[{"left":1,"count":0,"contribution":0},{"left":5,"count":1,"contribution":5}]}"#,
+        ));
+    }
+
+    #[test]
+    fn test_variant_from_name() {
+        assert_eq!(Part1Variant::from_name("radix").unwrap(), Part1Variant::Radix);
+        assert!(Part1Variant::from_name("bogo").is_err());
+    }
 }
@@ -1,11 +1,9 @@
 use anyhow::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
-use itertools::Itertools;
-use code_timing_macros::time_snippet;
+use std::collections::BTreeSet;
 use const_format::concatcp;
 use advent_of_code_2024::*;
+use advent_of_code_2024::report::{Format, Json, Table};
+use itertools::Itertools;
 
 const DAY: &str = "02";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -22,98 +20,553 @@ const TEST: &str = r#"
 
 "#;
 
-fn part1<R: BufRead>(reader: R) -> Result<usize> {
-    // TODO: Solve Part 1 of the puzzle
-    let mut result = 0;
+/// One report per non-empty line, each a list of levels
+fn parse_reports(bytes: &[u8]) -> Result<Vec<Vec<i32>>> {
+    let mut reports = Vec::new();
 
-    for line in reader.lines() {
-        let mut report_is_safe = true;
-
-        let line = line?;
-        let line = line.trim();
+    for line in input::lines(bytes) {
+        let line = line.trim_ascii();
         if line.is_empty() {
             continue;
         }
 
         let elements
-            = line.split_whitespace()
+            = input::fields(line)
             .map(
-                |s| s.parse::<i32>()
-                    .with_context(|| format!("Failed to parse number {}", s)))
+                |s| input::parse_i32(s)
+                    .ok_or_else(|| anyhow!("Failed to parse number {}", String::from_utf8_lossy(s))))
             .collect::<Result<Vec<_>>>()?;
 
-        let mut is_first_element = true;
-        let mut is_decreasing = true;
-
-        for window in elements.windows(2) {
-            if let [first, second] = window {
-                let diff = (first - second).abs();
-                if diff >= 1 && diff <= 3 || diff == 3 {
-                    if is_first_element {
-                        is_decreasing = first > second;
-                        is_first_element = false;
-                    } else if (first < second && is_decreasing) || (first > second && !is_decreasing) {
-                        report_is_safe = false;
-                        break;
+        reports.push(elements);
+    }
+
+    Ok(reports)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Increasing,
+    Decreasing,
+    /// Either way, but the same way throughout the report
+    Either,
+}
+
+impl Direction {
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "increasing" => Ok(Self::Increasing),
+            "decreasing" => Ok(Self::Decreasing),
+            "either" => Ok(Self::Either),
+            _ => bail!("Unknown direction '{}', expected increasing, decreasing or either", name),
+        }
+    }
+
+    /// The concrete directions a report may follow, as `increasing` flags
+    fn increasing(self) -> &'static [bool] {
+        match self {
+            Self::Increasing => &[true],
+            Self::Decreasing => &[false],
+            Self::Either => &[true, false],
+        }
+    }
+}
+
+/// What makes a report safe: how far adjacent levels may be apart and which way they must go
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SafetyRule {
+    min_step: u32,
+    max_step: u32,
+    direction: Direction,
+    /// Non-strict rules also accept equal adjacent levels
+    strict: bool,
+}
+
+impl SafetyRule {
+    /// The puzzle's rule: all increasing or all decreasing, by 1 to 3 at a time
+    const PUZZLE: Self = Self { min_step: 1, max_step: 3, direction: Direction::Either, strict: true };
+
+    /// The puzzle rule with whichever of `--min-step`, `--max-step`, `--direction` and
+    /// `--monotonic strict|non-strict` were given. `None` if none were.
+    fn from_options(runner: &Runner) -> Result<Option<Self>> {
+        let options = ["--min-step", "--max-step", "--direction", "--monotonic"];
+        if options.iter().all(|option| runner.option(option).is_none()) {
+            return Ok(None);
+        }
+
+        let mut rule = Self::PUZZLE;
+        if let Some(step) = runner.option("--min-step") {
+            rule.min_step = step.parse().with_context(|| format!("Invalid --min-step '{}'", step))?;
+        }
+        if let Some(step) = runner.option("--max-step") {
+            rule.max_step = step.parse().with_context(|| format!("Invalid --max-step '{}'", step))?;
+        }
+        if let Some(direction) = runner.option("--direction") {
+            rule.direction = Direction::from_name(direction)?;
+        }
+        if let Some(monotonic) = runner.option("--monotonic") {
+            rule.strict = match monotonic {
+                "strict" => true,
+                "non-strict" => false,
+                _ => bail!("Unknown --monotonic '{}', expected strict or non-strict", monotonic),
+            };
+        }
+        if rule.min_step > rule.max_step {
+            bail!("--min-step {} is larger than --max-step {}", rule.min_step, rule.max_step);
+        }
+        Ok(Some(rule))
+    }
+
+    fn is_safe_step(&self, from: i32, to: i32, increasing: bool) -> bool {
+        let diff = if increasing { to as i64 - from as i64 } else { from as i64 - to as i64 };
+        if diff == 0 {
+            return !self.strict;
+        }
+        (self.min_step as i64..=self.max_step as i64).contains(&diff)
+    }
+}
+
+impl std::fmt::Display for SafetyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = match self.direction {
+            Direction::Increasing => "increasing",
+            Direction::Decreasing => "decreasing",
+            Direction::Either => "either direction",
+        };
+        let monotonic = if self.strict { "strict" } else { "non-strict" };
+        write!(f, "steps {}-{}, {}, {}", self.min_step, self.max_step, direction, monotonic)
+    }
+}
+
+fn is_report_safe(elements: &[i32], rule: &SafetyRule) -> bool {
+    rule.direction.increasing().iter().any(|&increasing| {
+        elements.windows(2).all(|pair| rule.is_safe_step(pair[0], pair[1], increasing))
+    })
+}
+
+fn part1(reports: &[Vec<i32>]) -> Result<usize> {
+    Ok(reports.iter().filter(|report| is_report_safe(report, &SafetyRule::PUZZLE)).count())
+}
+
+/// Whether the report becomes safe with at most `tolerance` levels removed.
+///
+/// Rather than trying every combination of removed levels, this tracks for each level `i` and each
+/// number of removals `j` whether a safe run can end at `i` with `j` of the earlier levels removed.
+/// A run can only reach `i` from one of the `tolerance + 1` levels right before it, so a report of
+/// `n` levels takes O(n * tolerance²) steps, linear in its length.
+fn is_report_safe_with_tolerance(elements: &[i32], tolerance: usize, rule: &SafetyRule) -> bool {
+    let n = elements.len();
+    // A single level left is always safe
+    if n <= tolerance + 1 {
+        return true;
+    }
+
+    rule.direction.increasing().iter().any(|&increasing| {
+        // reachable[i * width + j]: a safe run ends at level i with j earlier levels removed
+        let width = tolerance + 1;
+        let mut reachable = vec![false; n * width];
+
+        for i in 0..n {
+            // The run may start here, with every level before it removed
+            if i <= tolerance {
+                reachable[i * width + i] = true;
+            }
+            for previous in i.saturating_sub(tolerance + 1)..i {
+                if !rule.is_safe_step(elements[previous], elements[i], increasing) {
+                    continue;
+                }
+                let skipped = i - previous - 1;
+                for removed in skipped..=tolerance {
+                    if reachable[previous * width + removed - skipped] {
+                        reachable[i * width + removed] = true;
                     }
-                } else {
-                    report_is_safe = false;
-                    break;
                 }
             }
+
+            // The run may also end here, with every level after it removed
+            let after = n - 1 - i;
+            if (0..=tolerance).any(|removed| reachable[i * width + removed] && removed + after <= tolerance) {
+                return true;
+            }
         }
+        false
+    })
+}
+
+/// Number of reports that are safe under `rule` with at most `tolerance` levels removed from each.
+fn count_safe(reports: &[Vec<i32>], tolerance: usize, rule: &SafetyRule) -> usize {
+    reports.iter().filter(|report| is_report_safe_with_tolerance(report, tolerance, rule)).count()
+}
 
-result += if report_is_safe { 1 } else { 0 };
+/// The Problem Dampener tolerates a single bad level
+fn part2(reports: &[Vec<i32>]) -> Result<usize> {
+    Ok(count_safe(reports, 1, &SafetyRule::PUZZLE))
 }
 
-Ok(result)
+//region Explain
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ViolationKind {
+    StepTooLarge,
+    StepTooSmall,
+    ZeroStep,
+    /// Going the other way than the steps before
+    DirectionChange,
+    /// Going the way the rule does not allow
+    WrongDirection,
 }
 
+impl ViolationKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::StepTooLarge => "step too large",
+            Self::StepTooSmall => "step too small",
+            Self::ZeroStep => "zero step",
+            Self::DirectionChange => "direction change",
+            Self::WrongDirection => "wrong direction",
+        }
+    }
+}
+
+/// Why a report is unsafe and what would fix it
+#[derive(Debug, Clone, PartialEq)]
+struct Explanation {
+    /// Index of the first level of the first offending pair, and what is wrong with the pair
+    violation: Option<(usize, ViolationKind)>,
+    /// The smallest sets of levels (by index) whose removal makes the report safe, if there are
+    /// any within the tolerance. At most [`MAX_FIXES`] of them.
+    fixes: Vec<Vec<usize>>,
+    /// Whether there are more fixes than are listed
+    truncated: bool,
+}
+
+impl Explanation {
+    fn verdict(&self) -> &'static str {
+        match (self.violation, self.fixes.is_empty()) {
+            (None, _) => "safe",
+            (Some(_), false) => "safe with removals",
+            (Some(_), true) => "unsafe",
+        }
+    }
+}
+
+/// Walks the report like the puzzle does, with the direction set by the first step that moves
+fn first_violation(elements: &[i32], rule: &SafetyRule) -> Option<(usize, ViolationKind)> {
+    let mut increasing = None;
+
+    for (i, pair) in elements.windows(2).enumerate() {
+        let diff = pair[1] as i64 - pair[0] as i64;
+        if diff == 0 {
+            if rule.strict {
+                return Some((i, ViolationKind::ZeroStep));
+            }
+            continue;
+        }
+
+        let goes_up = diff > 0;
+        match (increasing, rule.direction) {
+            (Some(expected), _) if expected != goes_up => return Some((i, ViolationKind::DirectionChange)),
+            (None, Direction::Increasing) if !goes_up => return Some((i, ViolationKind::WrongDirection)),
+            (None, Direction::Decreasing) if goes_up => return Some((i, ViolationKind::WrongDirection)),
+            _ => increasing = Some(goes_up),
+        }
+
+        if diff.abs() > rule.max_step as i64 {
+            return Some((i, ViolationKind::StepTooLarge));
+        }
+        if diff.abs() < rule.min_step as i64 {
+            return Some((i, ViolationKind::StepTooSmall));
+        }
+    }
+    None
+}
+
+/// A long report can have a huge number of smallest fixes, e.g. one per combination of duplicates
+const MAX_FIXES: usize = 100;
+
+/// Up to `limit` sets of exactly `removals` levels whose removal makes the report safe, sorted, and
+/// whether there are more. `removals` must be the fewest that work.
+///
+/// Like [`is_report_safe_with_tolerance`], but walking backwards: `finishes[i * width + r]` tells
+/// whether a safe run that keeps level `i`, with `r` earlier levels removed, can reach the end with
+/// exactly `removals` removed. Following only those states, every run that is started ends in a
+/// fix, so listing them costs O(n * removals) per fix rather than trying every combination.
+fn fixes_with_removals(elements: &[i32], removals: usize, rule: &SafetyRule, limit: usize) -> (Vec<Vec<usize>>, bool) {
+    let n = elements.len();
+    let width = removals + 1;
+    // A run of equal levels may be safe both ways, so the same fix can turn up twice
+    let mut fixes = BTreeSet::new();
+
+    'directions: for &increasing in rule.direction.increasing() {
+        let mut finishes = vec![false; n * width];
+        for i in (0..n).rev() {
+            for removed in 0..=removals {
+                let after = n - 1 - i;
+                finishes[i * width + removed] = removed + after == removals
+                    || (i + 1..n).take(removals - removed + 1).any(|next| {
+                        rule.is_safe_step(elements[i], elements[next], increasing)
+                            && finishes[next * width + removed + next - i - 1]
+                    });
+            }
+        }
+
+        // (kept level, levels removed so far, length of the run before it)
+        let mut stack: Vec<(usize, usize, usize)> = (0..=removals.min(n - 1)).rev()
+            .filter(|&first| finishes[first * width + first])
+            .map(|first| (first, first, 0))
+            .collect();
+        let mut kept = Vec::new();
+        while let Some((i, removed, depth)) = stack.pop() {
+            kept.truncate(depth);
+            kept.push(i);
+            if removed + (n - 1 - i) == removals {
+                fixes.insert((0..n).filter(|level| kept.binary_search(level).is_err()).collect::<Vec<_>>());
+                if fixes.len() > limit {
+                    break 'directions;
+                }
+                continue;
+            }
+            for next in (i + 1..n).take(removals - removed + 1).rev() {
+                let removed = removed + next - i - 1;
+                if rule.is_safe_step(elements[i], elements[next], increasing) && finishes[next * width + removed] {
+                    stack.push((next, removed, depth + 1));
+                }
+            }
+        }
+    }
+
+    let truncated = fixes.len() > limit;
+    (fixes.into_iter().take(limit).collect(), truncated)
+}
+
+fn explain(elements: &[i32], tolerance: usize, rule: &SafetyRule) -> Explanation {
+    let violation = first_violation(elements, rule);
+
+    let (mut fixes, mut truncated) = (Vec::new(), false);
+    if violation.is_some() {
+        // Only the smallest fixes, since any superset of a fix works as well
+        if let Some(removals) = (1..=tolerance).find(|&removals| is_report_safe_with_tolerance(elements, removals, rule)) {
+            (fixes, truncated) = fixes_with_removals(elements, removals, rule, MAX_FIXES);
+        }
+    }
+
+    Explanation { violation, fixes, truncated }
+}
+
+fn explanation_table(reports: &[Vec<i32>], explanations: &[Explanation]) -> Table {
+    let mut table = Table::new("Reports", &["#", "levels", "verdict", "pair", "violation", "fixing removals"]);
+    for (i, (report, explanation)) in reports.iter().zip(explanations).enumerate() {
+        let (pair, kind) = match explanation.violation {
+            Some((at, kind)) => (format!("{}-{}", at, at + 1), kind.name()),
+            None => ("-".to_string(), "-"),
+        };
+        let fixes = if explanation.fixes.is_empty() {
+            "-".to_string()
+        } else {
+            let fixes = explanation.fixes.iter().map(|fix| fix.iter().join(" ")).join("; ");
+            if explanation.truncated { format!("{}; ...", fixes) } else { fixes }
+        };
+        table.row([i.to_string(), report.iter().join(" "), explanation.verdict().to_string(), pair, kind.to_string(), fixes]);
+    }
+    table
+}
+
+fn explanation_json(reports: &[Vec<i32>], explanations: &[Explanation]) -> Json {
+    let indices = |values: &[usize]| Json::Array(values.iter().map(|&v| Json::number(v)).collect());
+
+    Json::Array(reports.iter().zip(explanations)
+        .map(|(report, explanation)| {
+            let violation = match explanation.violation {
+                Some((at, kind)) => Json::object([
+                    ("pair", indices(&[at, at + 1])),
+                    ("kind", Json::string(kind.name())),
+                ]),
+                None => Json::Null,
+            };
+            Json::object([
+                ("levels", Json::Array(report.iter().map(|&level| Json::number(level)).collect())),
+                ("verdict", Json::string(explanation.verdict())),
+                ("violation", violation),
+                ("fixes", Json::Array(explanation.fixes.iter().map(|fix| indices(fix)).collect())),
+                ("truncated", Json::Bool(explanation.truncated)),
+            ])
+        })
+        .collect())
+}
+//endregion
+
 fn main() -> Result<()> {
     start_day(DAY);
+    let mut runner = Runner::with_options(
+        DAY, INPUT_FILE, &["--min-step", "--max-step", "--direction", "--monotonic", "--tolerance", "--explain"])?;
 
-    //region Part 1
-    println!("=== Part 1 ===");
+    let custom_rule = SafetyRule::from_options(&runner)?;
+    let tolerance = match runner.option("--tolerance") {
+        Some(k) => Some(k.parse::<usize>().with_context(|| format!("Invalid --tolerance '{}'", k))?),
+        None => None,
+    };
+    let explain_format = runner.option("--explain").map(Format::from_name).transpose()?;
+
+    //region Parse
+    runner.section("Parse");
+
+    let parsed = runner.parse(|input| parse_reports(input.as_bytes()));
+    //endregion
 
-    assert_eq!(2, part1(BufReader::new(TEST.as_bytes()))?);
+    //region Part 1
+    runner.section("Part 1");
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file)?);
-    println!("Result = {}", result);
+    runner.example(2, || part1(&parse_reports(TEST.as_bytes())?));
+    runner.solve_expecting(&parsed, 680, |reports| part1(reports));
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part1(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part1(&parse_reports(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
     //endregion
 
     //region Part 2
-    // println!("\n=== Part 2 ===");
-    //
-    // fn part2<R: BufRead>(reader: R) -> Result<usize> {
-    //     Ok(0)
-    // }
-    //
-    // assert_eq!(0, part2(BufReader::new(TEST.as_bytes()))?);
-    //
-    // let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    // let result = time_snippet!(part2(input_file)?);
-    // println!("Result = {}", result);
+    runner.section("Part 2");
+
+    runner.example(4, || part2(&parse_reports(TEST.as_bytes())?));
+    runner.solve_expecting(&parsed, 710, |reports| part2(reports));
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part2(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part2(&parse_reports(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
+    //endregion
+
+    //region Custom rule
+    if custom_rule.is_some() || tolerance.is_some() {
+        runner.section("Custom rule");
+
+        let rule = custom_rule.unwrap_or(SafetyRule::PUZZLE);
+        let tolerance = tolerance.unwrap_or(0);
+        let label = format!("Result ({}, tolerance {})", rule, tolerance);
+        runner.solve_as(&parsed, &label, None, move |reports| Ok(count_safe(reports, tolerance, &rule)));
+    }
+    //endregion
+
+    //region Explain
+    if let Some(format) = explain_format {
+        runner.section("Explain");
+
+        let rule = custom_rule.unwrap_or(SafetyRule::PUZZLE);
+        let tolerance = tolerance.unwrap_or(0);
+        match parsed.default_input() {
+            None => println!("No parsed input to explain"),
+            Some(reports) => {
+                let explanations: Vec<Explanation> = reports.iter()
+                    .map(|report| explain(report, tolerance, &rule))
+                    .collect();
+                match format {
+                    Format::Table => println!("{}", explanation_table(reports, &explanations)),
+                    Format::Json => println!("{}", explanation_json(reports, &explanations)),
+                }
+            }
+        }
+    }
     //endregion
 
-    Ok(())
+    runner.finish()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2024::testing::XorShift;
+
+    /// Tries every way of removing up to `tolerance` levels
+    fn brute_force_safe(elements: &[i32], tolerance: usize, rule: &SafetyRule) -> bool {
+        if is_report_safe(elements, rule) {
+            return true;
+        }
+        tolerance > 0 && (0..elements.len()).any(|skipped| {
+            let without: Vec<i32> = elements.iter().enumerate()
+                .filter(|&(i, _)| i != skipped)
+                .map(|(_, &level)| level)
+                .collect();
+            brute_force_safe(&without, tolerance - 1, rule)
+        })
+    }
+
+    /// Deterministic pseudo-random reports, mostly close to safe so every tolerance matters
+    fn random_reports(count: usize, seed: u64) -> Vec<Vec<i32>> {
+        let mut rng = XorShift::new(seed);
+        let mut next = |bound: u64| rng.below(bound);
+        (0..count)
+            .map(|_| {
+                let len = next(9) as usize;
+                let mut level = next(20) as i32;
+                (0..len)
+                    .map(|_| {
+                        level += match next(10) {
+                            0 => 0,
+                            1 => -(next(5) as i32),
+                            2 => 4 + next(3) as i32,
+                            _ => 1 + next(3) as i32,
+                        };
+                        level
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tolerance_matches_brute_force() {
+        for (seed, tolerance) in (1..=20).zip((0..4).cycle()) {
+            for report in random_reports(500, seed) {
+                assert_eq!(
+                    is_report_safe_with_tolerance(&report, tolerance, &SafetyRule::PUZZLE),
+                    brute_force_safe(&report, tolerance, &SafetyRule::PUZZLE),
+                    "report {:?} with tolerance {}", report, tolerance,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_safe_by_tolerance() {
+        let reports = parse_reports(TEST.as_bytes()).unwrap();
+        let rule = SafetyRule::PUZZLE;
+        assert_eq!(count_safe(&reports, 0, &rule), part1(&reports).unwrap());
+        assert_eq!(count_safe(&reports, 1, &rule), 4);
+        // 1 2 7 8 9 needs both 1 and 2 removed, 9 7 6 2 1 both 2 and 1
+        assert_eq!(count_safe(&reports, 2, &rule), 6);
+        assert_eq!(count_safe(&[vec![], vec![5], vec![1, 1]], 0, &rule), 2);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let reports = parse_reports(TEST.as_bytes()).unwrap();
+        let rule = |min_step, max_step, direction, strict| SafetyRule { min_step, max_step, direction, strict };
+
+        // Only 7 6 4 2 1 is safe going down, 1 3 6 7 9 going up
+        assert_eq!(count_safe(&reports, 0, &rule(1, 3, Direction::Decreasing, true)), 1);
+        assert_eq!(count_safe(&reports, 0, &rule(1, 3, Direction::Increasing, true)), 1);
+        // A step of 4 makes 9 7 6 2 1 safe
+        assert_eq!(count_safe(&reports, 0, &rule(1, 4, Direction::Either, true)), 3);
+        // 8 6 4 4 1 only has a zero step and a step of 3
+        assert_eq!(count_safe(&reports, 0, &rule(1, 3, Direction::Either, false)), 3);
+        assert_eq!(count_safe(&reports, 0, &rule(2, 3, Direction::Either, false)), 1);
+        assert!(!is_report_safe(&[1, 1, 1], &rule(0, 3, Direction::Either, true)));
+        assert!(is_report_safe(&[1, 1, 1], &rule(0, 3, Direction::Increasing, false)));
+    }
+
+    #[test]
+    fn test_custom_rules_match_brute_force() {
+        let rules = [
+            SafetyRule { min_step: 1, max_step: 2, direction: Direction::Increasing, strict: true },
+            SafetyRule { min_step: 2, max_step: 5, direction: Direction::Decreasing, strict: false },
+            SafetyRule { min_step: 1, max_step: 3, direction: Direction::Either, strict: false },
+        ];
+        for (seed, tolerance) in (1..=12).zip((0..3).cycle()) {
+            let rule = &rules[seed as usize % rules.len()];
+            for report in random_reports(300, seed) {
+                assert_eq!(
+                    is_report_safe_with_tolerance(&report, tolerance, rule),
+                    brute_force_safe(&report, tolerance, rule),
+                    "report {:?} with tolerance {} under {}", report, tolerance, rule,
+                );
+            }
+        }
+    }
 
     #[test]
     fn test_part1_1() {
@@ -126,7 +579,7 @@ mod tests {
             100 101 102 103 105 106 104
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        let result = part1(&parse_reports(input.as_bytes()).unwrap()).unwrap();
 
         assert_eq!(result, 3);
     }
@@ -138,7 +591,7 @@ mod tests {
             8 9 8
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        let result = part1(&parse_reports(input.as_bytes()).unwrap()).unwrap();
 
         assert_eq!(result, 0);
     }
@@ -149,7 +602,7 @@ mod tests {
             100 101 102 103 105 106 103
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        let result = part1(&parse_reports(input.as_bytes()).unwrap()).unwrap();
 
         assert_eq!(result, 0);
     }
@@ -160,11 +613,169 @@ mod tests {
             7 6 4 2 1
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        let result = part1(&parse_reports(input.as_bytes()).unwrap()).unwrap();
 
         assert_eq!(result, 1);
     }
 
+    #[test]
+    fn test_part2_1() {
+        let input = r#"
+            1 3 2 4 5
+            8 6 4 4 1
+            1 2 7 8 9
+        "#;
+
+        let result = part2(&parse_reports(input.as_bytes()).unwrap()).unwrap();
+
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn test_part2_2() {
+        // Removing the first or the last level fixes these
+        let input = r#"
+            9 1 2 3 4
+            1 2 3 4 9
+            5 1 2 3 4
+            1 2 3 4 3
+        "#;
+
+        let result = part2(&parse_reports(input.as_bytes()).unwrap()).unwrap();
+
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn test_part2_3() {
+        // Two bad levels are one too many
+        let input = r#"
+            1 2 9 10 3 4
+            1 1 1 2
+            5 4 8 9 3 2
+        "#;
+
+        let result = part2(&parse_reports(input.as_bytes()).unwrap()).unwrap();
+
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_part2_4() {
+        // The first pair sets the wrong direction, which only removing one of them fixes
+        let input = r#"
+            3 4 3 2 1
+            2 1 2 3 4
+        "#;
+
+        let result = part2(&parse_reports(input.as_bytes()).unwrap()).unwrap();
+
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn test_explain_example() {
+        let reports = parse_reports(TEST.as_bytes()).unwrap();
+        let explanations: Vec<Explanation> = reports.iter().map(|r| explain(r, 1, &SafetyRule::PUZZLE)).collect();
+
+        let violations: Vec<Option<(usize, ViolationKind)>> = explanations.iter().map(|e| e.violation).collect();
+        assert_eq!(violations, vec![
+            None,
+            Some((1, ViolationKind::StepTooLarge)),
+            Some((2, ViolationKind::StepTooLarge)),
+            Some((1, ViolationKind::DirectionChange)),
+            Some((2, ViolationKind::ZeroStep)),
+            None,
+        ]);
+        let verdicts: Vec<&str> = explanations.iter().map(Explanation::verdict).collect();
+        assert_eq!(verdicts, vec!["safe", "unsafe", "unsafe", "safe with removals", "safe with removals", "safe"]);
+        // Removing the 3 or the 2 of 1 3 2 4 5 works, but only one 4 of 8 6 4 4 1
+        assert_eq!(explanations[3].fixes, vec![vec![1], vec![2]]);
+        assert_eq!(explanations[4].fixes, vec![vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_explain_smallest_fixes_and_json() {
+        let reports = vec![vec![1, 9, 2, 8, 3]];
+        let explanations = vec![explain(&reports[0], 2, &SafetyRule::PUZZLE)];
+        assert_eq!(explanations[0].fixes, vec![vec![1, 3]]);
+        assert_eq!(
+            explanation_json(&reports, &explanations).to_string(),
+            r#"[{"levels":[1,9,2,8,3],"verdict":"safe with removals","violation":{"pair":[0,1],"kind":"step too large"},"fixes":[[1,3]],"truncated":false}]"#,
+        );
+
+        let decreasing = SafetyRule { direction: Direction::Decreasing, ..SafetyRule::PUZZLE };
+        assert_eq!(first_violation(&[1, 2], &decreasing), Some((0, ViolationKind::WrongDirection)));
+        let wide = SafetyRule { min_step: 2, ..SafetyRule::PUZZLE };
+        assert_eq!(first_violation(&[1, 3, 4], &wide), Some((1, ViolationKind::StepTooSmall)));
+    }
+
+    #[test]
+    fn test_explain_agrees_with_safety() {
+        let rules = [
+            SafetyRule::PUZZLE,
+            SafetyRule { min_step: 2, max_step: 5, direction: Direction::Decreasing, strict: false },
+            SafetyRule { min_step: 1, max_step: 3, direction: Direction::Either, strict: false },
+        ];
+        for (seed, rule) in (1..=6).zip(rules.iter().cycle()) {
+            for report in random_reports(300, seed) {
+                let explanation = explain(&report, 1, rule);
+                assert_eq!(explanation.violation.is_none(), is_report_safe(&report, rule), "{:?} under {}", report, rule);
+                assert_eq!(explanation.verdict() != "unsafe", is_report_safe_with_tolerance(&report, 1, rule));
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_fixes_match_brute_force() {
+        let rules = [
+            SafetyRule::PUZZLE,
+            SafetyRule { min_step: 1, max_step: 3, direction: Direction::Either, strict: false },
+        ];
+        for (seed, rule) in (1..=8).zip(rules.iter().cycle()) {
+            for report in random_reports(200, seed) {
+                let explanation = explain(&report, 2, rule);
+                let mut expected = Vec::new();
+                if explanation.violation.is_some() {
+                    for removals in 1..=2.min(report.len()) {
+                        expected = (0..report.len())
+                            .combinations(removals)
+                            .filter(|removed| {
+                                let kept: Vec<i32> = report.iter().enumerate()
+                                    .filter(|(i, _)| !removed.contains(i))
+                                    .map(|(_, &level)| level)
+                                    .collect();
+                                is_report_safe(&kept, rule)
+                            })
+                            .collect();
+                        if !expected.is_empty() {
+                            break;
+                        }
+                    }
+                }
+                assert_eq!(explanation.fixes, expected, "{:?} under {}", report, rule);
+                assert!(!explanation.truncated);
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_truncates_many_fixes() {
+        // One of each pair of equal levels has to go, in any of 2^7 ways
+        let report: Vec<i32> = (1..=7).flat_map(|level| [level, level]).collect();
+        let explanation = explain(&report, 7, &SafetyRule::PUZZLE);
+        assert_eq!(explanation.fixes.len(), MAX_FIXES);
+        assert!(explanation.truncated);
+        assert!(explanation.fixes.iter().all(|fix| fix.len() == 7 && fix.iter().all(|&i| report[i] == report[i ^ 1])));
+        assert!(explanation_table(&[report], &[explanation]).to_string().contains("; ..."));
+
+        // A long report with a high tolerance is explained without trying every combination
+        let mut report: Vec<i32> = (0..3000).collect();
+        report[1000] = 5000;
+        let explanation = explain(&report, 4, &SafetyRule::PUZZLE);
+        assert_eq!(explanation.fixes, vec![vec![1000]]);
+    }
+
     #[test]
     fn test_test_input() {
         let input = r#"
@@ -176,8 +787,24 @@ mod tests {
             1 3 6 7 9
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        let reports = parse_reports(input.as_bytes()).unwrap();
 
-        assert_eq!(result, 2);
+        assert_eq!(part1(&reports).unwrap(), 2);
+        assert_eq!(part2(&reports).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_negative_levels() {
+        let input = r#"
+            -3 -1 0 2
+            -5 -7 -10
+            4 -4
+        "#;
+
+        let reports = parse_reports(input.as_bytes()).unwrap();
+
+        assert_eq!(reports[0], vec![-3, -1, 0, 2]);
+        assert_eq!(part1(&reports).unwrap(), 2);
+        assert!(parse_reports(b"1 --2").is_err());
     }
 }
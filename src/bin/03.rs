@@ -1,16 +1,14 @@
+use std::result::Result::Ok;
 use anyhow::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
-use regex::Regex;
-use code_timing_macros::time_snippet;
+use std::io::{ErrorKind, Read};
 use const_format::concatcp;
 use advent_of_code_2024::*;
+use advent_of_code_2024::report::{Format, Json, Table};
 
 const DAY: &str = "03";
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
 // TODO: Get big boy input
-const INPUT_FILE_BIG_BOY: &str = concatcp!("input/", "bigboy", DAY, ".txt");
+// const INPUT_FILE_BIG_BOY: &str = concatcp!("input/", "bigboy", DAY, ".txt");
 
 const TEST_PT1: &str = r#"\
 xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))
@@ -20,337 +18,1079 @@ const TEST_PT2: &str = r#"\
 xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))
 "#;
 
-const MULT_RE: &str = r"mul\((\d{1,3}),(\d{1,3})\)";
+//region Instruction sets
+/// The most operands any instruction takes
+const MAX_OPERANDS: usize = 4;
+
+/// What an instruction takes between its parentheses
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operands {
+    /// `name()`
+    None,
+    /// `name(1,22,333)`: this many comma-separated numbers of 1 to 3 digits
+    Numbers(usize),
+}
+
+impl Operands {
+    fn count(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Numbers(count) => count,
+        }
+    }
+}
+
+/// What the instructions act on
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Machine {
+    accumulator: i64,
+    enabled: bool,
+    /// How many conditionals are open, for instruction sets that nest them
+    depth: usize,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self { accumulator: 0, enabled: true, depth: 0 }
+    }
+}
+
+/// One instruction the scanner recognizes as `name(operands)`. Names must not contain digits,
+/// commas or parentheses.
+#[derive(Clone, Copy)]
+struct Instruction {
+    name: &'static str,
+    operands: Operands,
+    action: fn(&mut Machine, &[i64]),
+}
+
+struct InstructionSet {
+    name: &'static str,
+    instructions: &'static [Instruction],
+}
+
+fn mul(machine: &mut Machine, operands: &[i64]) {
+    machine.accumulator += operands[0] * operands[1];
+}
+
+fn mul_if_enabled(machine: &mut Machine, operands: &[i64]) {
+    if machine.enabled {
+        mul(machine, operands);
+    }
+}
+
+fn add_if_enabled(machine: &mut Machine, operands: &[i64]) {
+    if machine.enabled {
+        machine.accumulator += operands[0] + operands[1];
+    }
+}
+
+fn enable(machine: &mut Machine, _: &[i64]) {
+    machine.enabled = true;
+}
 
-fn part1<R: BufRead>(reader: R) -> Result<usize> {
-    let mut result: usize = 0;
+fn disable(machine: &mut Machine, _: &[i64]) {
+    machine.enabled = false;
+}
+
+fn toggle(machine: &mut Machine, _: &[i64]) {
+    machine.enabled = !machine.enabled;
+}
+
+fn open_conditional(machine: &mut Machine, _: &[i64]) {
+    machine.depth += 1;
+    machine.enabled = false;
+}
+
+fn close_conditional(machine: &mut Machine, _: &[i64]) {
+    machine.depth = machine.depth.saturating_sub(1);
+    machine.enabled = machine.depth == 0;
+}
 
-    let re = Regex::new(MULT_RE)?;
+const MUL: Instruction = Instruction { name: "mul", operands: Operands::Numbers(2), action: mul };
+const MUL_IF_ENABLED: Instruction = Instruction { name: "mul", operands: Operands::Numbers(2), action: mul_if_enabled };
+const DO: Instruction = Instruction { name: "do", operands: Operands::None, action: enable };
+const DONT: Instruction = Instruction { name: "don't", operands: Operands::None, action: disable };
+
+/// Every `mul` counts
+const PART1: InstructionSet = InstructionSet { name: "part1", instructions: &[MUL] };
+
+/// `don't()` disables the `mul`s that follow until the next `do()`
+const PART2: InstructionSet = InstructionSet { name: "part2", instructions: &[MUL_IF_ENABLED, DO, DONT] };
+
+/// Like part 2, with `add` as well
+const ADD: InstructionSet = InstructionSet {
+    name: "add",
+    instructions: &[
+        MUL_IF_ENABLED,
+        Instruction { name: "add", operands: Operands::Numbers(2), action: add_if_enabled },
+        DO,
+        DONT,
+    ],
+};
+
+/// A single `toggle()` switches the `mul`s off and on
+const TOGGLE: InstructionSet = InstructionSet {
+    name: "toggle",
+    instructions: &[
+        MUL_IF_ENABLED,
+        Instruction { name: "toggle", operands: Operands::None, action: toggle },
+    ],
+};
+
+/// Every `don't()` needs its own `do()` before the `mul`s count again
+const NESTED: InstructionSet = InstructionSet {
+    name: "nested",
+    instructions: &[
+        MUL_IF_ENABLED,
+        Instruction { name: "do", operands: Operands::None, action: close_conditional },
+        Instruction { name: "don't", operands: Operands::None, action: open_conditional },
+    ],
+};
+
+const INSTRUCTION_SETS: [&InstructionSet; 5] = [&PART1, &PART2, &ADD, &TOGGLE, &NESTED];
+
+impl InstructionSet {
+    fn from_name(name: &str) -> Result<&'static Self> {
+        INSTRUCTION_SETS.into_iter()
+            .find(|set| set.name == name)
+            .ok_or_else(|| anyhow!("Unknown instruction set '{}', expected one of {}",
+                name, INSTRUCTION_SETS.map(|set| set.name).join(", ")))
+    }
+}
+//endregion
+
+//region Grammar
+/// How strictly instructions are written. The instruction sets say which instructions there are,
+/// this says what counts as writing them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Grammar {
+    name: &'static str,
+    min_digits: usize,
+    max_digits: usize,
+    /// Spaces and tabs allowed around operands, e.g. `mul( 5, 6 )`
+    whitespace: bool,
+    /// A `+` or `-` allowed in front of operands
+    signed: bool,
+    case_sensitive: bool,
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
+impl Grammar {
+    /// Exactly what the puzzle accepts
+    const STRICT: Self = Self {
+        name: "strict",
+        min_digits: 1,
+        max_digits: 3,
+        whitespace: false,
+        signed: false,
+        case_sensitive: true,
+    };
+
+    /// What a human would still read as an instruction, like `MUL(5, -6)`
+    const LENIENT: Self = Self {
+        name: "lenient",
+        min_digits: 1,
+        max_digits: 6,
+        whitespace: true,
+        signed: true,
+        case_sensitive: false,
+    };
+
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "strict" => Ok(Self::STRICT),
+            "lenient" => Ok(Self::LENIENT),
+            _ => bail!("Unknown grammar '{}', expected strict or lenient", name),
         }
+    }
+
+    fn name_matches(&self, expected: u8, byte: u8) -> bool {
+        if self.case_sensitive { expected == byte } else { expected.eq_ignore_ascii_case(&byte) }
+    }
 
-        result += re.captures_iter(line)
-            .map(|cap| {
-                // lammma remove
-                let left: usize = cap[1].parse().with_context(|| format!("Error parsing {}", line))?;
-                let right: usize  = cap[2].parse().with_context(|| format!("Error parsing {}", line))?;
+    fn is_whitespace(&self, byte: u8) -> bool {
+        self.whitespace && (byte == b' ' || byte == b'\t')
+    }
+}
+//endregion
+
+//region Scanner
+/// An instruction found in the input
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Call {
+    /// Index into the instruction set
+    instruction: usize,
+    values: [i64; MAX_OPERANDS],
+    /// Offsets of the first byte and one past the closing parenthesis
+    start: usize,
+    end: usize,
+}
 
-                Ok(left * right)
-            }
-            ).collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .sum::<usize>();
-    }
-
-    Ok(result)
-}
-
-/// Finds the first occurrence of a string within a specified range.
-/// Either range can be unprovided, in which case, it is treated as if the entire string will be searched.
-///
-/// Returns (usize, usize): The start and end of the string
-fn find_next_substring_in_range(
-    str: &str,
-    sub_str: &str,
-    start: Option<usize>,
-    end: Option<usize>) -> Option<(usize, usize)>
-{
-    let str_len = str.len();
-    let sub_len = sub_str.len();
-    if str_len < sub_len
-    {
-        return None;
+impl Call {
+    fn operands<'a>(&'a self, set: &InstructionSet) -> &'a [i64] {
+        &self.values[..set.instructions[self.instruction].operands.count()]
+    }
+}
+
+/// How much of a possible instruction has been seen
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    /// Right after the instruction's name, where only `(` may follow
+    Open { instruction: usize },
+    /// Past `name(`, with `count` operands done and `digits` digits of the next one
+    Operands { instruction: usize, values: [i64; MAX_OPERANDS], count: usize, digits: usize, operand: Operand },
+}
+
+/// Where in an operand the scanner is, beyond the digits it counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    /// Nothing but whitespace yet
+    Before,
+    /// After its sign, or in its digits
+    Inside { negative: bool },
+    /// Whitespace after the digits, so only more whitespace or a separator may follow
+    After { negative: bool },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Partial {
+    start: usize,
+    phase: Phase,
+}
+
+enum Advance {
+    Continue(Partial),
+    Reject,
+    Complete(Call),
+}
+
+/// Byte-at-a-time matcher for the instructions of a set. The names are found by a `MultiMatcher`,
+/// and from there the scanner follows every candidate that is still possible. It keeps no input
+/// besides them, so it can be fed from any number of buffers in a row. Candidates are at most a few
+/// bytes long, so there are only ever a handful of them.
+struct Scanner<'a> {
+    set: &'a InstructionSet,
+    grammar: Grammar,
+    names: MultiMatcher,
+    search: SearchState,
+    partials: Vec<Partial>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(set: &'a InstructionSet, grammar: Grammar) -> Self {
+        for instruction in set.instructions {
+            assert!(instruction.operands.count() <= MAX_OPERANDS, "Too many operands for {}", instruction.name);
+        }
+
+        let names = set.instructions.iter().map(|instruction| instruction.name);
+        let names = if grammar.case_sensitive {
+            MultiMatcher::new(names)
+        } else {
+            MultiMatcher::with_ascii_case_insensitive(names)
+        };
+        Self { set, grammar, names, search: SearchState::default(), partials: Vec::new() }
+    }
+
+    /// Takes the next byte and returns the instruction it completes, if any.
+    fn feed(&mut self, byte: u8) -> Option<Call> {
+        self.feed_with(byte, |_| {})
     }
 
-    let lower_bound = start.unwrap_or(0);
-    let upper_bound = end.unwrap_or(str_len);
+    /// Like `feed`, and hands every candidate the byte rejects after its full name to `on_near_miss`.
+    fn feed_with<F: FnMut(NearMiss)>(&mut self, byte: u8, mut on_near_miss: F) -> Option<Call> {
+        let Self { set, grammar, names, search, partials } = self;
+        let offset = search.offset();
+
+        let mut completed = None;
+        if !partials.is_empty() {
+            // Candidates are kept in the order they started, so the first one to complete is the
+            // leftmost
+            partials.retain_mut(|partial| match advance(set, grammar, *partial, byte, offset) {
+                Advance::Continue(next) => {
+                    *partial = next;
+                    true
+                }
+                Advance::Reject => {
+                    if let Some(miss) = near_miss(set, grammar, *partial, Some(byte), offset) {
+                        on_near_miss(miss);
+                    }
+                    false
+                }
+                Advance::Complete(call) => {
+                    completed.get_or_insert(call);
+                    false
+                }
+            });
+        }
+        if completed.is_some() {
+            // Whatever else was going on overlaps the instruction
+            partials.clear();
+        }
 
-    if lower_bound >= upper_bound
-        || (upper_bound - lower_bound) <= sub_len-1
-        || upper_bound > str_len {
-        return None;
+        // Names never contain a `)`, so none can end with the byte that completed an instruction.
+        // Longer names come first, so candidates stay in the order they started.
+        names.feed(search, byte, |name| {
+            partials.push(Partial { start: name.start, phase: Phase::Open { instruction: name.pattern } });
+        });
+        completed
     }
 
-    let search_range = &str[lower_bound..upper_bound].as_bytes();
-    for i in 0..search_range.len() {
-        if lower_bound + i + sub_len > upper_bound {
-            return None;
+    /// Feeds a whole chunk and hands every instruction it completes to `on_call`. With `Scan::Fast`,
+    /// the bytes that cannot start a name are skipped while no candidate is open.
+    fn feed_chunk<F: FnMut(&Self, Call)>(&mut self, chunk: &[u8], scan: Scan, mut on_call: F) {
+        let mut i = 0;
+        while i < chunk.len() {
+            if scan == Scan::Fast && self.partials.is_empty() {
+                i += self.names.skip(&mut self.search, &chunk[i..]);
+                if i == chunk.len() {
+                    break;
+                }
+            }
+            if let Some(call) = self.feed(chunk[i]) {
+                on_call(self, call);
+            }
+            i += 1;
         }
+    }
 
-        for (j, c_sub) in sub_str.char_indices() {
-            let c_str = search_range[i+j] as char;
-            if c_sub != c_str {  // Nonmatching. End immediately.
-                break;
-            } else if c_sub == c_str && j == sub_len-1 {  // The final character matches, so return the location.
-                return Some((lower_bound + i, lower_bound + i + sub_len));
+    /// Reports the candidates the input ended in the middle of.
+    fn finish<F: FnMut(NearMiss)>(&mut self, mut on_near_miss: F) {
+        for partial in self.partials.drain(..) {
+            if let Some(miss) = near_miss(self.set, &self.grammar, partial, None, self.search.offset()) {
+                on_near_miss(miss);
             }
         }
     }
+}
 
-    None
+fn advance(set: &InstructionSet, grammar: &Grammar, partial: Partial, byte: u8, offset: usize) -> Advance {
+    let instructions = set.instructions;
+    let phase = match partial.phase {
+        Phase::Open { instruction } if byte == b'(' => Phase::Operands {
+            instruction,
+            values: [0; MAX_OPERANDS],
+            count: 0,
+            digits: 0,
+            operand: Operand::Before,
+        },
+        Phase::Open { .. } => return Advance::Reject,
+        Phase::Operands { instruction, mut values, count, digits, operand } => {
+            let expected = instructions[instruction].operands.count();
+            let enough_digits = digits >= grammar.min_digits;
+            // Applies the sign once the operand is complete
+            let finish = |values: &mut [i64; MAX_OPERANDS]| {
+                if let Operand::Inside { negative: true } | Operand::After { negative: true } = operand {
+                    values[count] = -values[count];
+                }
+            };
+            match (byte, operand) {
+                (b'0'..=b'9', Operand::Before | Operand::Inside { .. }) if count < expected && digits < grammar.max_digits => {
+                    values[count] = values[count] * 10 + (byte - b'0') as i64;
+                    let negative = matches!(operand, Operand::Inside { negative: true });
+                    Phase::Operands { instruction, values, count, digits: digits + 1, operand: Operand::Inside { negative } }
+                }
+                (b'+' | b'-', Operand::Before) if grammar.signed && count < expected => {
+                    let operand = Operand::Inside { negative: byte == b'-' };
+                    Phase::Operands { instruction, values, count, digits, operand }
+                }
+                (_, Operand::Before) if grammar.is_whitespace(byte) => partial.phase,
+                (_, Operand::Inside { negative } | Operand::After { negative }) if digits > 0 && grammar.is_whitespace(byte) => {
+                    Phase::Operands { instruction, values, count, digits, operand: Operand::After { negative } }
+                }
+                (b',', _) if enough_digits && count + 1 < expected => {
+                    finish(&mut values);
+                    Phase::Operands { instruction, values, count: count + 1, digits: 0, operand: Operand::Before }
+                }
+                (b')', Operand::Before) if expected == 0 => {
+                    return Advance::Complete(Call { instruction, values, start: partial.start, end: offset + 1 });
+                }
+                (b')', _) if enough_digits && count + 1 == expected => {
+                    finish(&mut values);
+                    return Advance::Complete(Call { instruction, values, start: partial.start, end: offset + 1 });
+                }
+                _ => return Advance::Reject,
+            }
+        }
+    };
+    Advance::Continue(Partial { start: partial.start, phase })
 }
 
-/// Within a given range of a string, sum all mul(x, y) pairs
-fn sum_all_mul_pairs_in_range(str: &str, start: usize, end: usize) -> usize {
-    let slice = &str[start..end];
+/// An instruction that got as far as its full name, but no further
+#[derive(Debug, Clone, PartialEq)]
+struct NearMiss {
+    instruction: usize,
+    /// Offsets of the first byte and one past the offending one
+    start: usize,
+    end: usize,
+    reason: String,
+}
 
-    let re = Regex::new(MULT_RE).unwrap();
+/// Why `found` (`None` at the end of the input) does not continue `partial`, if `partial` is far
+/// enough along to be worth mentioning.
+fn near_miss(set: &InstructionSet, grammar: &Grammar, partial: Partial, found: Option<u8>, offset: usize) -> Option<NearMiss> {
+    let instructions = set.instructions;
+    let (instruction, expected) = match partial.phase {
+        Phase::Open { instruction } => {
+            // Not a near-miss if the name goes on into a longer one, like `do` into `don't`
+            let name = instructions[instruction].name.as_bytes();
+            let continues = |other: &Instruction| {
+                let other = other.name.as_bytes();
+                other.len() > name.len()
+                    && other.iter().zip(name).all(|(&a, &b)| grammar.name_matches(a, b))
+                    && found.is_some_and(|byte| grammar.name_matches(other[name.len()], byte))
+            };
+            if instructions.iter().any(continues) {
+                return None;
+            }
+            (instruction, "'('")
+        }
+        Phase::Operands { instruction, count, digits, operand, .. } => {
+            let separator = if count + 1 < instructions[instruction].operands.count() { "','" } else { "')'" };
+            let expected = match (instructions[instruction].operands, operand) {
+                (Operands::None, _) => "')'",
+                (_, Operand::After { .. }) => separator,
+                _ if digits < grammar.min_digits => "a digit",
+                _ if digits == grammar.max_digits && found.is_some_and(|b| b.is_ascii_digit()) => {
+                    let reason = format!("operand has more than {} digits", grammar.max_digits);
+                    return Some(NearMiss { instruction, start: partial.start, end: offset + 1, reason });
+                }
+                _ if digits == grammar.max_digits => separator,
+                _ if separator == "','" => "a digit or ','",
+                _ => "a digit or ')'",
+            };
+            (instruction, expected)
+        }
+    };
+
+    let (found, end) = match found {
+        Some(byte) => (format!("'{}'", (byte as char).escape_default()), offset + 1),
+        None => ("the end of the input".to_string(), offset),
+    };
+    let reason = format!("expected {} but found {}", expected, found);
+    Some(NearMiss { instruction, start: partial.start, end, reason })
+}
+//endregion
+
+//region Trace
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    /// Whether the machine was enabled when it got to the instruction, and what it added to the sum
+    Executed { enabled: bool, contribution: i64 },
+    Rejected(String),
+}
 
-    re.captures_iter(slice)
-        .map(|cap| {
-            // lammma remove
-            let left: usize = cap[1].parse().with_context(|| format!("Error parsing {}", slice))?;
-            let right: usize  = cap[2].parse().with_context(|| format!("Error parsing {}", slice))?;
+/// Something the scanner found, with the byte range it covers
+#[derive(Debug, Clone, PartialEq)]
+struct TraceEntry {
+    name: &'static str,
+    start: usize,
+    end: usize,
+    outcome: Outcome,
+}
 
-            Ok(left * right)
-        }
-        ).collect::<Result<Vec<_>>>().unwrap()
-        .into_iter()
-        .sum::<usize>()
-}
-
-/// All ranges of values where mul will be enabled.
-/// So all ranges from the start to the first don't(), and then repeatedly from the next do()
-/// to the next don't().
-fn do_dont_bounds(str: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
-    let mut result: Vec<(usize, usize)> = Vec::new();
-    let mut finding_do = false;
-    let mut string_to_find = "don't()";
-    let mut last_upper_bound = 0;
-
-    while let Some((lower_bound, upper_bound))
-        = find_next_substring_in_range(&str, string_to_find, Some(last_upper_bound), None)
-    {
-        if finding_do {
-            string_to_find = "don't()";
-        } else {
-            result.push((last_upper_bound, lower_bound));
-            string_to_find = "do()";
+/// Every instruction the set recognizes and every near-miss, in the order they start
+fn trace(input: &[u8], set: &InstructionSet, grammar: Grammar) -> Vec<TraceEntry> {
+    let mut scanner = Scanner::new(set, grammar);
+    let mut machine = Machine::default();
+    let mut entries = Vec::new();
+
+    let mut rejected = |miss: NearMiss| entries.push(TraceEntry {
+        name: set.instructions[miss.instruction].name,
+        start: miss.start,
+        end: miss.end,
+        outcome: Outcome::Rejected(miss.reason),
+    });
+    let mut executed = Vec::new();
+
+    for &byte in input {
+        if let Some(call) = scanner.feed_with(byte, &mut rejected) {
+            let instruction = &set.instructions[call.instruction];
+            let (enabled, before) = (machine.enabled, machine.accumulator);
+            (instruction.action)(&mut machine, call.operands(set));
+            executed.push(TraceEntry {
+                name: instruction.name,
+                start: call.start,
+                end: call.end,
+                outcome: Outcome::Executed { enabled, contribution: machine.accumulator - before },
+            });
         }
-        finding_do = !finding_do;
+    }
+    scanner.finish(&mut rejected);
+
+    entries.extend(executed);
+    entries.sort_by_key(|entry| entry.start);
+    entries
+}
 
-        // do some processing between the bounds of the string, between the "don't()" and the "do()"
-        last_upper_bound = upper_bound;
+/// 1-based line and char column of every offset, looked up through the offsets lines start at.
+/// Columns count the bytes that are not UTF-8 continuation bytes, like `MultiMatcher` does.
+struct LineIndex<'a> {
+    input: &'a [u8],
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        let newlines = input.iter().enumerate().filter(|(_, &b)| b == b'\n').map(|(i, _)| i + 1);
+        Self { input, starts: std::iter::once(0).chain(newlines).collect() }
     }
 
-    if !finding_do {
-        result.push((last_upper_bound, str.len()));
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let chars = self.input[self.starts[line - 1]..offset].iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        (line, chars + 1)
     }
+}
 
-    result.into_iter()
+fn trace_text(input: &[u8], entry: &TraceEntry) -> String {
+    String::from_utf8_lossy(&input[entry.start..entry.end]).to_string()
 }
 
-fn part2<R: BufRead>(reader: R) -> Result<usize> {
-    let mut result: usize = 0;
+fn trace_table(input: &[u8], entries: &[TraceEntry]) -> Table {
+    let lines = LineIndex::new(input);
+    let mut table = Table::new("Trace", &["offset", "line", "column", "text", "enabled", "contribution", "rejected because"]);
+    for entry in entries {
+        let (line, column) = lines.position(entry.start);
+        let (enabled, contribution, reason) = match &entry.outcome {
+            Outcome::Executed { enabled, contribution } => (enabled.to_string(), contribution.to_string(), String::new()),
+            Outcome::Rejected(reason) => ("-".to_string(), "-".to_string(), reason.clone()),
+        };
+        let text = trace_text(input, entry).escape_debug().to_string();
+        table.row([entry.start.to_string(), line.to_string(), column.to_string(), text, enabled, contribution, reason]);
+    }
+    table
+}
+
+fn trace_json(input: &[u8], entries: &[TraceEntry]) -> Json {
+    let lines = LineIndex::new(input);
+    Json::Array(entries.iter()
+        .map(|entry| {
+            let (line, column) = lines.position(entry.start);
+            let mut fields = vec![
+                ("instruction", Json::string(entry.name)),
+                ("offset", Json::number(entry.start)),
+                ("line", Json::number(line)),
+                ("column", Json::number(column)),
+                ("text", Json::string(trace_text(input, entry))),
+            ];
+            match &entry.outcome {
+                Outcome::Executed { enabled, contribution } => {
+                    fields.push(("enabled", Json::Bool(*enabled)));
+                    fields.push(("contribution", Json::number(contribution)));
+                }
+                Outcome::Rejected(reason) => fields.push(("rejected", Json::string(reason.as_str()))),
+            }
+            Json::object(fields)
+        })
+        .collect())
+}
+//endregion
+
+//region Scan
+/// How the scanners go through the input, selected with `--scan`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scan {
+    /// Jump between the bytes an instruction name can start with (`m` and `d` for part 2), which
+    /// are found 16 or 8 bytes at a time. Only those spots are validated byte by byte.
+    Fast,
+    /// Feed every byte to the scanner
+    Bytewise,
+}
 
-    // filter out any error lines and collect all lines into one long string
-    let flattened_input: String = reader
-        .lines()
-        .filter_map(|line| line.ok())
-        .collect();
+impl Scan {
+    const ALL: [Scan; 2] = [Self::Fast, Self::Bytewise];
 
-    result += do_dont_bounds(flattened_input.as_str())
-        .map(
-            |(start, end)| sum_all_mul_pairs_in_range(flattened_input.as_str(), start, end)
-        )
-        .sum::<usize>();
+    fn name(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Bytewise => "bytewise",
+        }
+    }
 
-    Ok(result)
+    fn from_name(name: &str) -> Result<Self> {
+        Self::ALL.into_iter()
+            .find(|scan| scan.name() == name)
+            .ok_or_else(|| anyhow!("Unknown scan '{}', expected one of fast, bytewise or all", name))
+    }
+}
+//endregion
+
+/// Size of the buffer the input is read through
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the input through one buffer of `chunk_size` bytes, so memory stays the same however
+/// large the input is.
+fn for_each_chunk<R: Read, F: FnMut(&[u8])>(mut reader: R, chunk_size: usize, mut f: F) -> Result<()> {
+    let mut buffer = vec![0; chunk_size.max(1)];
+    let mut offset = 0;
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read input at byte {}", offset)),
+        };
+        f(&buffer[..read]);
+        offset += read;
+    }
+}
+
+/// Runs each instruction set on its own machine, all in a single pass over the input.
+/// Instructions may span any number of chunks, since the scanners carry their state from one chunk
+/// to the next. Lines mean nothing here: a newline simply is not part of any instruction.
+fn run_chunked<R: Read>(reader: R, sets: &[&InstructionSet], grammar: Grammar, scan: Scan, chunk_size: usize) -> Result<Vec<Machine>> {
+    let mut scanners: Vec<Scanner> = sets.iter().map(|set| Scanner::new(set, grammar)).collect();
+    let mut machines = vec![Machine::default(); sets.len()];
+
+    for_each_chunk(reader, chunk_size, |chunk| {
+        for (scanner, machine) in scanners.iter_mut().zip(&mut machines) {
+            scanner.feed_chunk(chunk, scan, |scanner, call| {
+                let instruction = &scanner.set.instructions[call.instruction];
+                (instruction.action)(machine, call.operands(scanner.set));
+            });
+        }
+    })?;
+
+    Ok(machines)
+}
+
+fn run<R: Read>(reader: R, sets: &[&InstructionSet], grammar: Grammar) -> Result<Vec<Machine>> {
+    run_chunked(reader, sets, grammar, Scan::Fast, CHUNK_SIZE)
+}
+
+fn part1_with<R: Read>(reader: R, scan: Scan) -> Result<i64> {
+    Ok(run_chunked(reader, &[&PART1], Grammar::STRICT, scan, CHUNK_SIZE)?[0].accumulator)
+}
+
+fn part2_with<R: Read>(reader: R, scan: Scan) -> Result<i64> {
+    Ok(run_chunked(reader, &[&PART2], Grammar::STRICT, scan, CHUNK_SIZE)?[0].accumulator)
+}
+
+fn part1<R: Read>(reader: R) -> Result<i64> {
+    part1_with(reader, Scan::Fast)
+}
+
+fn part2<R: Read>(reader: R) -> Result<i64> {
+    part2_with(reader, Scan::Fast)
 }
 
 fn main() -> Result<()> {
     start_day(DAY);
+    let mut runner = Runner::with_options(DAY, INPUT_FILE, &["--instructions", "--grammar", "--trace", "--scan"])?;
+    let custom_set = runner.option("--instructions").map(InstructionSet::from_name).transpose()?;
+    let grammar = runner.option("--grammar").map(Grammar::from_name).transpose()?;
+    let trace_format = runner.option("--trace").map(Format::from_name).transpose()?;
+    // `--scan all` runs both scans, which together with `bench` compares their throughput
+    let scans = match runner.option("--scan") {
+        Some("all") => Scan::ALL.to_vec(),
+        Some(name) => vec![Scan::from_name(name)?],
+        None => vec![Scan::Fast],
+    };
+
+    //region Parse
+    runner.section("Parse");
+
+    // The corrupted memory is scanned as is, so this only maps the file
+    let parsed = runner.parse(Ok);
+    //endregion
 
     //region Part 1
-    println!("=== Part 1 ===");
-
-    assert_eq!(161, part1(BufReader::new(TEST_PT1.as_bytes()))?);
-
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file)?);
-    println!("Result = {}", result);
+    runner.section("Part 1");
+
+    runner.example(161, || part1(TEST_PT1.as_bytes()));
+    for &scan in &scans {
+        let label = match scan {
+            Scan::Fast => "Result".to_string(),
+            _ => format!("Result ({})", scan.name()),
+        };
+        runner.solve_as(&parsed, &label, Some(164730528), move |input| part1_with(input.reader(), scan));
+    }
 
-    // let result = time_snippet!(part1(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // TODO: Uncomment for big boy result
+    // runner.part("Result (big boy)", || part1(Input::open(INPUT_FILE_BIG_BOY)?.reader()));
     //endregion
 
     //region Part 2
-    println!("\n=== Part 2 ===");
+    runner.section("Part 2");
+
+    runner.example(48, || part2(TEST_PT2.as_bytes()));
+    for &scan in &scans {
+        let label = match scan {
+            Scan::Fast => "Result".to_string(),
+            _ => format!("Result ({})", scan.name()),
+        };
+        runner.solve_as(&parsed, &label, Some(70478672), move |input| part2_with(input.reader(), scan));
+    }
 
-    assert_eq!(48, part2(BufReader::new(TEST_PT2.as_bytes()))?);
+    // TODO: Uncomment for big boy result
+    // runner.part("Result (big boy)", || part2(Input::open(INPUT_FILE_BIG_BOY)?.reader()));
+    //endregion
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part2(input_file)?);
-    println!("Result = {}", result);
+    //region Instruction set
+    if custom_set.is_some() || grammar.is_some() {
+        runner.section("Instruction set");
+
+        // A different grammar alone applies to both parts
+        let sets = match custom_set {
+            Some(set) => vec![set],
+            None => vec![&PART1, &PART2],
+        };
+        let grammar = grammar.unwrap_or(Grammar::STRICT);
+        for set in sets {
+            let label = format!("Result ({}, {})", set.name, grammar.name);
+            runner.solve_as(&parsed, &label, None, move |input| Ok(run(input.reader(), &[set], grammar)?[0].accumulator));
+        }
+    }
+    //endregion
 
-    // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part2(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    //region Trace
+    if let Some(format) = trace_format {
+        runner.section("Trace");
+
+        match parsed.default_input() {
+            None => println!("No parsed input to trace"),
+            Some(input) => {
+                let entries = trace(input.as_bytes(), custom_set.unwrap_or(&PART2), grammar.unwrap_or(Grammar::STRICT));
+                match format {
+                    Format::Table => println!("{}", trace_table(input.as_bytes(), &entries)),
+                    Format::Json => println!("{}", trace_json(input.as_bytes(), &entries)),
+                }
+            }
+        }
+    }
     //endregion
 
-    Ok(())
+    runner.finish()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2024::testing::XorShift;
+
+    /// Part 1 with the lenient grammar, since the puzzle does not allow spaces in `mul(5, 6)`
+    fn part1_lenient(input: &str) -> i64 {
+        run(input.as_bytes(), &[&PART1], Grammar::LENIENT).unwrap()[0].accumulator
+    }
 
     #[test]
-    #[ignore]  // TODO: Figure out why this is failing
     fn test_part1_simple() {
         let input = r#"
             mul(5, 6)
         "#;
 
-        let result = part1(input.as_bytes()).unwrap();
+        assert_eq!(part1(input.as_bytes()).unwrap(), 0);
+        let result = part1_lenient(input);
         assert_eq!(result, 30);
     }
 
     #[test]
-    #[ignore]  // TODO: Figure out why this is failing
     fn test_part1_invalid_character() {
         let input = r#"
             #*mul(5, 6)%
         "#;
-        let result = part1(input.as_bytes()).unwrap();
+        assert_eq!(part1(input.as_bytes()).unwrap(), 0);
+        let result = part1_lenient(input);
         assert_eq!(result, 30);
     }
 
     #[test]
-    fn test_regex() {
+    fn test_lenient_grammar() {
+        assert_eq!(part1_lenient("mul( 5 ,\t6 )"), 30);
+        assert_eq!(part1_lenient("MUL(-5,+6)Mul(1000,2)"), -30 + 2000);
+        assert_eq!(part1_lenient("mul(5 5,6)mul(- 5,6)mul(5,6"), 0);
+        assert_eq!(run("DON'T()mul(1,1)Do()mul(2,2)".as_bytes(), &[&PART2], Grammar::LENIENT).unwrap()[0].accumulator, 4);
+        // The strict grammar stays the puzzle's
+        assert_eq!(part1("MUL(1,1)mul(-1,1)mul(1000,1)mul( 1,1)".as_bytes()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_grammar_digit_range() {
+        let grammar = Grammar { min_digits: 2, max_digits: 4, ..Grammar::STRICT };
+        let sum = |input: &str| run(input.as_bytes(), &[&PART1], grammar).unwrap()[0].accumulator;
+        assert_eq!(sum("mul(10,1000)mul(1,10)mul(10,10000)"), 10000);
+        assert_eq!(Grammar::from_name("lenient").unwrap(), Grammar::LENIENT);
+        assert!(Grammar::from_name("loose").is_err());
+    }
+
+    #[test]
+    fn test_lenient_near_misses() {
+        let entries = trace("mul( 5 6)mul(1234567,1)".as_bytes(), &PART1, Grammar::LENIENT);
+        let reasons: Vec<&Outcome> = entries.iter().map(|entry| &entry.outcome).collect();
+        assert_eq!(reasons, vec![
+            &Outcome::Rejected("expected ',' but found '6'".to_string()),
+            &Outcome::Rejected("operand has more than 6 digits".to_string()),
+        ]);
+    }
+
+    /// Every instruction of part 2 with its operands and the offset it starts at
+    fn tokens(input: &str) -> Vec<(&'static str, Vec<i64>, usize)> {
+        let mut scanner = Scanner::new(&PART2, Grammar::STRICT);
+        input.bytes()
+            .filter_map(|byte| scanner.feed(byte))
+            .map(|call| (PART2.instructions[call.instruction].name, call.operands(&PART2).to_vec(), call.start))
+            .collect()
+    }
+
+    #[test]
+    fn test_mul_syntax() {
         let input = r#" mul(1,333)&& mul(222,3) |@()%)&(^! mut(mut(555, 3)) mul(2222,3) *mul(1) ^&  mul(33) mul(553,333)&&"#;
-        let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
-        let result = re.captures_iter(input).count();
+        let muls: Vec<Vec<i64>> = tokens(input).into_iter().map(|(_, operands, _)| operands).collect();
 
-        // Extract and print the first match
-        for cap in re.captures_iter(input) {
-            let first_value: usize = cap[1].parse().unwrap(); // Parse the first captured group
-            let second_value: usize = cap[2].parse().unwrap(); // Parse the second captured group
-        }
+        assert_eq!(muls, vec![vec![1, 333], vec![222, 3], vec![553, 333]]);
+    }
 
-        assert_eq!(result, 3);
+    #[test]
+    fn test_restarts_inside_candidates() {
+        assert_eq!(tokens("mumul(2,3)"), vec![("mul", vec![2, 3], 2)]);
+        assert_eq!(tokens("mul(2,mul(3,4)"), vec![("mul", vec![3, 4], 6)]);
+        assert_eq!(tokens("dodo()don'don't()"), vec![("do", vec![], 2), ("don\'t", vec![], 10)]);
+        assert_eq!(tokens("mul(,3)mul(3,)mul()mul(1,2,3)"), vec![]);
     }
 
     #[test]
-    fn test_find_next_substring_in_range_simple_1() {
-        let str: &str = "do()";
+    fn test_tokens_simple_1() {
+        assert_eq!(tokens("do()"), vec![("do", vec![], 0)]);
+    }
 
-        let bounds: Option<(usize, usize)>
-            = find_next_substring_in_range(str, "do()", None, None);
+    #[test]
+    fn test_tokens_simple_2() {
+        assert_eq!(tokens("aaaaa do() bbbbb"), vec![("do", vec![], 6)]);
+    }
 
-        match bounds {
-            Some((start, end)) => {
-                assert_eq!(start, 0);
-                assert_eq!(end, 4);
-            },
-            _ => {
-                assert!(false, "The match was not found");
-            }
-        }
+    #[test]
+    fn test_tokens_exact_range() {
+        assert_eq!(tokens("fffffdo()ffff"), vec![("do", vec![], 5)]);
+    }
+
+    #[test]
+    fn test_tokens_cut_off() {
+        assert_eq!(tokens("fffffdo("), vec![]);
+        assert_eq!(tokens("fffffmul(1,2"), vec![]);
     }
 
     #[test]
-    fn test_find_next_substring_in_range_simple_2() {
-        let str: &str = "aaaaa do() bbbbb";
+    fn test_do_dont_no_match()
+    {
+        assert_eq!(tokens("aaaadon'taaaadoaaadodon't(aaadon't)"), vec![]);
+    }
 
-        let bounds: Option<(usize, usize)>
-            = find_next_substring_in_range(str, "do()", None, None);
+    #[test]
+    fn test_do_dont_single_dont()
+    {
+        assert_eq!(tokens("aaaadon'taaaadoaaadodon't()aaadon't)"), vec![("don\'t", vec![], 20)]);
+    }
 
-        match bounds {
-            Some((start, end)) => {
-                assert_eq!(start, 6);
-                assert_eq!(end, 10);
-            },
-            _ => {
-                assert!(false, "The match was not found");
-            }
-        }
+    #[test]
+    fn test_do_dont_simple_1()
+    {
+        assert_eq!(tokens("aaaadon't()aaaado()aaado()don't()aaadon't()"), vec![
+            ("don\'t", vec![], 4),
+            ("do", vec![], 15),
+            ("do", vec![], 22),
+            ("don\'t", vec![], 26),
+            ("don\'t", vec![], 36),
+        ]);
     }
 
     #[test]
-    fn test_find_next_substring_in_range_exact_range() {
-        let str: &str = "fffffdo()ffff";
+    fn test_do_dont_simple_2()
+    {
+        assert_eq!(tokens("aaaadon't()aaaado()a"), vec![("don\'t", vec![], 4), ("do", vec![], 15)]);
+    }
 
-        let bounds: Option<(usize, usize)>
-            = find_next_substring_in_range(str, "do()", Some(5), Some(9));
+    /// Hands out at most `limit` bytes per read, then fails once `fail_at` bytes have been read
+    struct FlakyReader<'a> {
+        data: &'a [u8],
+        limit: usize,
+        fail_at: Option<usize>,
+        read: usize,
+    }
 
-        match bounds {
-            Some((start, end)) => {
-                assert_eq!(start, 5);
-                assert_eq!(end, 9);
-            },
-            _ => {
-                assert!(false, "The match was not found");
+    impl Read for FlakyReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.fail_at.is_some_and(|at| self.read >= at) {
+                return Err(std::io::Error::new(ErrorKind::InvalidData, "unreadable line"));
             }
+            let n = buf.len().min(self.limit).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            self.read += n;
+            Ok(n)
         }
     }
 
     #[test]
-    fn test_find_next_substring_in_range_too_small_range() {
-        let str: &str = "fffffdo()ffff";
-
-        let bounds: Option<(usize, usize)>
-            = find_next_substring_in_range(str, "do()", Some(5), Some(8));
+    fn test_tokens_across_chunk_boundaries() {
+        let input = "mul(123,456)xdon't()mul(2,\n3)mul(4,5)do()mul(999,999)\nmu\nl(1,1)";
+        let expected = vec![123 * 456 + 20 + 999 * 999, 123 * 456 + 999 * 999];
+        let accumulators = |machines: Vec<Machine>| machines.iter().map(|m| m.accumulator).collect::<Vec<_>>();
+
+        for scan in Scan::ALL {
+            for chunk_size in 1..=input.len() {
+                let machines = run_chunked(input.as_bytes(), &[&PART1, &PART2], Grammar::STRICT, scan, chunk_size).unwrap();
+                assert_eq!(accumulators(machines), expected, "{:?} scan, chunk size {}", scan, chunk_size);
+            }
+            for limit in 1..8 {
+                let reader = FlakyReader { data: input.as_bytes(), limit, fail_at: None, read: 0 };
+                let machines = run_chunked(reader, &[&PART1, &PART2], Grammar::STRICT, scan, 5).unwrap();
+                assert_eq!(accumulators(machines), expected, "{:?} scan, reads of {} bytes", scan, limit);
+            }
+        }
+    }
 
-        match bounds {
-            Some((start, end)) => {
-                assert!(false, "The match was found, which is not what we want");
-            },
-            _ => {
-                assert!(true, "The match was not found, which is what we want");
+    #[test]
+    fn test_fast_scan_matches_bytewise() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        let mut next = move || rng.next_u64();
+        // Mostly noise, with enough pieces of instructions to form some and nearly form many more
+        const PIECES: [&str; 14] = ["mul(", "MUL(", "do()", "don't()", "Do(", "toggle()", "add(", "1", "23", ",", ")", " ", "x", "é"];
+
+        for _ in 0..200 {
+            let input: String = (0..next() % 300)
+                .map(|_| match next() % 4 {
+                    0 => PIECES[(next() % PIECES.len() as u64) as usize],
+                    _ => "qz",
+                })
+                .collect();
+            for set in INSTRUCTION_SETS {
+                for grammar in [Grammar::STRICT, Grammar::LENIENT] {
+                    let run_scan = |scan| run_chunked(input.as_bytes(), &[set], grammar, scan, 7).unwrap()[0].accumulator;
+                    assert_eq!(run_scan(Scan::Fast), run_scan(Scan::Bytewise), "{} {} on {:?}", set.name, grammar.name, input);
+                }
             }
         }
     }
 
     #[test]
-    fn test_find_do_dont_bounds_no_match()
-    {
-        let str: &str = "aaaadon'taaaadoaaadodon't(aaadon't)";
+    fn test_scan_from_name() {
+        assert_eq!(Scan::from_name("bytewise").unwrap(), Scan::Bytewise);
+        assert!(Scan::from_name("simd").is_err());
+    }
 
-        let mut bounds = do_dont_bounds(str);
+    #[test]
+    fn test_unreadable_input_is_an_error() {
+        let reader = FlakyReader { data: b"mul(2,3)mul(4,5)", limit: 4, fail_at: Some(8), read: 0 };
+        let err = run_chunked(reader, &[&PART2], Grammar::STRICT, Scan::Fast, 16).unwrap_err();
+        assert!(format!("{:#}", err).contains("at byte 8: unreadable line"), "{:#}", err);
+    }
 
-        assert_eq!(bounds.next(), Some((0, str.len())));
+    #[test]
+    fn test_both_parts_in_one_pass() {
+        let machines = run(TEST_PT2.as_bytes(), &[&PART1, &PART2], Grammar::STRICT).unwrap();
+        assert_eq!(machines[0].accumulator, 161);
+        assert_eq!(machines[1].accumulator, 48);
+        // `undo()` contains a `do()`
+        assert!(machines[1].enabled);
     }
 
     #[test]
-    fn test_find_do_dont_bounds_single_dont()
-    {
-        let str: &str = "aaaadon'taaaadoaaadodon't()aaadon't)";
+    fn test_instruction_set_variants() {
+        let run_set = |set: &InstructionSet, input: &str| run(input.as_bytes(), &[set], Grammar::STRICT).unwrap()[0].accumulator;
+
+        let input = "mul(2,3)add(4,5)don't()add(1,1)mul(1,1)do()add(10,20)";
+        assert_eq!(run_set(&PART2, input), 6);
+        assert_eq!(run_set(&ADD, input), 6 + 9 + 30);
 
-        let mut bounds = do_dont_bounds(str);
+        let input = "mul(2,3)toggle()mul(4,5)toggle()mul(6,7)toggle(1)mul(1,1)";
+        assert_eq!(run_set(&TOGGLE, input), 6 + 42 + 1);
 
-        assert_eq!(bounds.next(), Some((0, 20)));
+        let input = "mul(1,1)don't()don't()mul(2,2)do()mul(3,3)do()mul(4,4)do()mul(5,5)";
+        assert_eq!(run_set(&PART2, input), 1 + 9 + 16 + 25);
+        assert_eq!(run_set(&NESTED, input), 1 + 16 + 25);
     }
 
     #[test]
-    fn test_find_do_dont_bounds_simple_1()
-    {
-        let str: &str = "aaaadon't()aaaado()aaado()don't()aaadon't()";
+    fn test_shared_name_prefixes() {
+        fn count(machine: &mut Machine, operands: &[i64]) {
+            machine.accumulator += 1 + operands.iter().sum::<i64>();
+        }
+        const SET: InstructionSet = InstructionSet {
+            name: "test",
+            instructions: &[
+                Instruction { name: "ab", operands: Operands::Numbers(1), action: count },
+                Instruction { name: "abc", operands: Operands::Numbers(3), action: count },
+                Instruction { name: "b", operands: Operands::None, action: count },
+            ],
+        };
+        let machines = run("ab(1)abc(1,2,3)abc(1)aab(10)ab()abb()".as_bytes(), &[&SET], Grammar::STRICT).unwrap();
+        // `ab()` and `abb()` are no `ab`, but both end in `b()`
+        assert_eq!(machines[0].accumulator, 2 + 7 + 11 + 1 + 1);
+    }
 
-        let mut bounds = do_dont_bounds(str);
+    /// What the trace says about each entry, in order
+    fn traced(input: &str) -> Vec<(String, Outcome)> {
+        trace(input.as_bytes(), &PART2, Grammar::STRICT).iter()
+            .map(|entry| (trace_text(input.as_bytes(), entry), entry.outcome.clone()))
+            .collect()
+    }
+
+    fn rejected(text: &str, reason: &str) -> (String, Outcome) {
+        (text.to_string(), Outcome::Rejected(reason.to_string()))
+    }
 
-        assert_eq!(bounds.next(), Some((0, 4)));
-        assert_eq!(bounds.next(), Some((19, 26)));
+    fn executed(text: &str, enabled: bool, contribution: i64) -> (String, Outcome) {
+        (text.to_string(), Outcome::Executed { enabled, contribution })
     }
 
     #[test]
-    fn test_find_do_dont_bounds_simple_2()
-    {
-        let str: &str = "aaaadon't()aaaado()a";
+    fn test_trace_example() {
+        assert_eq!(traced(TEST_PT2.trim()), vec![
+            executed("mul(2,4)", true, 8),
+            rejected("mul[", "expected '(' but found '['"),
+            executed("don't()", true, 0),
+            executed("mul(5,5)", false, 0),
+            rejected("mul(32,64]", "expected a digit or ')' but found ']'"),
+            executed("mul(11,8)", false, 0),
+            executed("do()", false, 0),
+            executed("mul(8,5)", true, 40),
+        ]);
+    }
 
-        let mut bounds = do_dont_bounds(str);
+    #[test]
+    fn test_trace_explains_ignored_tests() {
+        assert_eq!(traced("#*mul(5, 6)%"), vec![rejected("mul(5, ", "expected a digit but found ' '")]);
+        assert_eq!(traced("mul(1234,5)mul(1)"), vec![
+            rejected("mul(1234", "operand has more than 3 digits"),
+            rejected("mul(1)", "expected a digit or ',' but found ')'"),
+        ]);
+        assert_eq!(traced("do_\ndon't(x"), vec![
+            rejected("do_", "expected '(' but found '_'"),
+            rejected("don't(x", "expected ')' but found 'x'"),
+        ]);
+        assert_eq!(traced("mul(2,"), vec![rejected("mul(2,", "expected a digit but found the end of the input")]);
+    }
 
-        assert_eq!(bounds.next(), Some((0, 4)));
-        assert_eq!(bounds.next(), Some((19, str.len())));
+    #[test]
+    fn test_trace_positions() {
+        let input = "ab\nxmul(2,3)\n\n  do()";
+        let entries = trace(input.as_bytes(), &PART2, Grammar::STRICT);
+        let lines = LineIndex::new(input.as_bytes());
+        let positions: Vec<(usize, usize)> = entries.iter().map(|entry| lines.position(entry.start)).collect();
+        assert_eq!(positions, vec![(2, 2), (4, 3)]);
+
+        let json = trace_json(input.as_bytes(), &entries[..1]).to_string();
+        assert_eq!(json, r#"[{"instruction":"mul","offset":4,"line":2,"column":2,"text":"mul(2,3)","enabled":true,"contribution":6}]"#);
+    }
+
+    #[test]
+    fn test_multibyte_text() {
+        let input = "€mul(2,3)ädo()\nümul(4,5)ö";
+        assert_eq!(part1(input.as_bytes()).unwrap(), 26);
+
+        let entries = trace(input.as_bytes(), &PART2, Grammar::STRICT);
+        let lines = LineIndex::new(input.as_bytes());
+        let found: Vec<(&str, usize, (usize, usize))> = entries.iter()
+            .map(|entry| (&input[entry.start..entry.end], entry.start, lines.position(entry.start)))
+            .collect();
+        assert_eq!(found, vec![("mul(2,3)", 3, (1, 2)), ("do()", 13, (1, 11)), ("mul(4,5)", 20, (2, 2))]);
+    }
+
+    #[test]
+    fn test_instruction_set_from_name() {
+        assert_eq!(InstructionSet::from_name("nested").unwrap().name, "nested");
+        assert!(InstructionSet::from_name("mul").is_err());
     }
 
     #[test]
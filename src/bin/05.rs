@@ -1,11 +1,14 @@
 // use std::error::Error;
 use anyhow::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
-use code_timing_macros::time_snippet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
 use const_format::concatcp;
+use itertools::Itertools;
 use advent_of_code_2024::*;
+use advent_of_code_2024::report::{Format, Json, Table};
 
 const DAY: &str = "05"; // TODO: Fill the day
 const INPUT_FILE: &str = concatcp!("input/", DAY, ".txt");
@@ -43,51 +46,101 @@ const TEST: &str = r#"
     97,13,75,29,47
 "#; // TODO: Enter test input
 
-fn parse_file<R: BufRead>(mut reader: R) -> Result<(Vec<(usize, usize)>, Vec<Vec<usize>>)> {
-    let mut content = String::new();
-    reader.read_to_string(&mut content)?;
-
-    let mut parts = content.split("\n\n");
-
-    let constraints = parts.next()
-        .unwrap_or("")
-        .lines()
-        .map(str::trim)
-        .filter(|l| !l.is_empty())
-        .map(|l| {
-            let mut split = l.split("|");
-            let first_str = split.next().ok_or_else(|| anyhow!("Missing first number in part1 line"))?.trim();
-            let second_str = split.next().ok_or_else(|| anyhow!("Missing first number in part1 line"))?.trim();
-            if split.next().is_some() {
-                Err(anyhow!("Extra data in part1 line"))
-            } else {
-                let first = first_str.parse::<usize>().map_err(|_| anyhow!("Invalid number '{}'", first_str.trim()))?;
-                let second = second_str.parse::<usize>().map_err(|_| anyhow!("Invalid number '{}'", second_str.trim()))?;
-                Ok((first, second))
-            }
-        })
-        .collect::<Result<Vec<(usize, usize)>>>()?;
-
-    let sequences = parts.next()
-        .unwrap_or("")
-        .lines()
-        .map(str::trim)
-        .filter(|l| !l.is_empty())
-        .map(|l| {
-            l.split(",")
-                .map(|s| s.trim().parse::<usize>().map_err(|_| anyhow!("Invalid number '{}'", s.trim())))
-                .collect::<Result<Vec<usize>>>()
-        })
-        .collect::<Result<Vec<Vec<usize>>>>()?;
+/// Dense indices for the page labels, in the order they first appear in the input. Pages are compared as written, so
+/// any label works, e.g. `4000000000` or `cover`, and `047` is a different page than `47`. Everything else refers to
+/// pages by index, so memory depends only on how many different pages there are. Each label is allocated once and
+/// shared between `labels` and the lookup `index`.
+#[derive(Debug, Default)]
+struct Pages {
+    labels: Vec<Arc<str>>,
+    index: HashMap<Arc<str>, usize>,
+}
+
+impl Pages {
+    fn intern(&mut self, label: &str) -> Result<usize> {
+        if label.is_empty() || label.contains(char::is_whitespace) {
+            bail!("Invalid page '{}'", label);
+        }
+        if let Some(&page) = self.index.get(label) {
+            return Ok(page);
+        }
+        let label: Arc<str> = Arc::from(label);
+        let page = self.labels.len();
+        self.labels.push(Arc::clone(&label));
+        self.index.insert(label, page);
+        Ok(page)
+    }
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn label(&self, page: usize) -> &str {
+        &self.labels[page]
+    }
+
+    /// The page's label as a number, for adding up the middle pages
+    fn number(&self, page: usize) -> Result<usize> {
+        let label = self.label(page);
+        label.parse().map_err(|_| anyhow!("Page '{}' is not a number, so it can't be added up", label))
+    }
+
+    /// The labels of an update like `[75, 47, 61]`
+    fn update(&self, seq: &[usize]) -> String {
+        format!("[{}]", seq.iter().map(|&page| self.label(page)).join(", "))
+    }
+}
 
-    Ok((constraints, sequences))
+/// An ordering rule `before|after`, between interned pages
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rule {
+    before: usize,
+    after: usize,
+    /// 1-based line of the input the rule is on
+    line: usize,
 }
 
-fn parse_adj_list(edge_tuples: Vec<(usize, usize)>) -> Result<Vec<Vec<usize>>> {
-    // find max number in among all tuple-pairs
-    let max = edge_tuples.iter().map(|(a, b)| *a.max(b)).max().unwrap_or(0);
-    
-    let mut adj_list = vec![Vec::new(); max + 1];
+/// The `a|b` ordering rules and the comma-separated updates, with the labels of their pages
+type PuzzleInput = (Vec<Rule>, Vec<Vec<usize>>, Pages);
+
+fn parse_file(bytes: &[u8]) -> Result<PuzzleInput> {
+    let mut pages = Pages::default();
+    let mut intern = |label: &[u8]| {
+        let label = std::str::from_utf8(label.trim_ascii()).context("Page is not valid UTF-8")?;
+        pages.intern(label)
+    };
+
+    // The rules are the first block and the updates every block after it. An empty line before anything else means
+    // there are no rules, while a single newline right at the start, as the examples have, is not an empty line.
+    let leading_newlines = bytes.iter().take_while(|b| b.is_ascii_whitespace()).filter(|&&b| b == b'\n').count();
+    let mut blocks = input::blocks(bytes);
+    let rule_block = if leading_newlines < 2 { blocks.next().unwrap_or_default() } else { &[] };
+
+    let mut constraints = Vec::new();
+    for (i, line) in input::lines(rule_block).enumerate() {
+        let mut split = line.trim_ascii().split(|&b| b == b'|');
+        let first_str = split.next().ok_or_else(|| anyhow!("Missing first number in part1 line"))?;
+        let second_str = split.next().ok_or_else(|| anyhow!("Missing second number in part1 line"))?;
+        if split.next().is_some() {
+            bail!("Extra data in part1 line");
+        }
+        let first = intern(first_str)?;
+        let second = intern(second_str)?;
+        // The block starts right after the leading blank lines
+        constraints.push(Rule { before: first, after: second, line: leading_newlines + i + 1 });
+    }
+
+    let mut sequences = Vec::new();
+    for line in blocks.flat_map(input::lines) {
+        sequences.push(line.trim_ascii().split(|&b| b == b',').map(&mut intern).collect::<Result<Vec<usize>>>()?);
+    }
+
+    Ok((constraints, sequences, pages))
+}
+
+/// The rules by page, with an entry for each of `page_count` interned pages
+fn parse_adj_list(edge_tuples: Vec<(usize, usize)>, page_count: usize) -> Result<Vec<Vec<usize>>> {
+    let mut adj_list = vec![Vec::new(); page_count];
 
     for (a, b) in edge_tuples {
         adj_list[a].push(b);
@@ -95,24 +148,20 @@ fn parse_adj_list(edge_tuples: Vec<(usize, usize)>) -> Result<Vec<Vec<usize>>> {
     Ok(adj_list)
 }
 
-fn find_max_in_all_sequences(sequences: &Vec<Vec<usize>>) -> usize {
-    *(sequences.iter().map(|seq| seq.iter().max().unwrap_or(&0)).max().unwrap_or(&0))
-}
-
-/// Pass by reference an array which can hold as many elements as the largest element in the ordering
-fn initialize_positional_array(ordering: &Vec<usize>, positional_array: &mut Vec<usize>) {
+/// Pass by reference an array which can hold an element for every page
+fn initialize_positional_array(ordering: &[usize], positional_array: &mut [usize]) {
     positional_array.fill(usize::MAX);
     for (i, n) in ordering.iter().enumerate() {
         positional_array[*n] = i;
     }
 }
 
-fn verify_ordering(adj_list: &Vec<Vec<usize>>, ordering: &Vec<usize>, positional_array: &mut Vec<usize>) -> bool {
+fn verify_ordering(adj_list: &[Vec<usize>], ordering: &[usize], positional_array: &mut [usize]) -> bool {
     for n in ordering.iter() {
         let n: usize = *n;
         // Check each value against its constraints. All values in the adjacency list for this element must have a
         // position further to the right in the ordering.
-        for &m in &adj_list[n as usize] {
+        for &m in &adj_list[n] {
             // If any values are -1, they are not in the ordering and we can skip them
             if positional_array[m] < positional_array[n] {
                 // If the position of the adjacent element is less than the current element, the ordering is invalid
@@ -123,129 +172,629 @@ fn verify_ordering(adj_list: &Vec<Vec<usize>>, ordering: &Vec<usize>, positional
     true
 }
 
-fn part1<R: BufRead>(reader: R) -> Result<usize> {
-    let mut result: usize = 0;
+/// Everything both parts need, built once from the input
+struct PrintQueue {
+    adj_list: Vec<Vec<usize>>,
+    /// The line of the first rule for each `(before, after)` pair
+    rule_lines: HashMap<(usize, usize), usize>,
+    sequences: Vec<Vec<usize>>,
+    pages: Pages,
+}
 
-    let (constraints, sequences) = parse_file(reader)?;
+fn parse_print_queue(bytes: &[u8]) -> Result<PrintQueue> {
+    let (rules, sequences, pages) = parse_file(bytes)?;
 
-    // find the largest value among the sequences
-    let max_value: usize = find_max_in_all_sequences(&sequences);
-    let mut positional_array: Vec<usize> = vec![usize::MAX; max_value + 1];
+    let adj_list = parse_adj_list(rules.iter().map(|rule| (rule.before, rule.after)).collect(), pages.len())?;
+    let mut rule_lines = HashMap::new();
+    for rule in &rules {
+        rule_lines.entry((rule.before, rule.after)).or_insert(rule.line);
+    }
 
-    let adj_list = parse_adj_list(constraints)?;
+    Ok(PrintQueue { adj_list, rule_lines, sequences, pages })
+}
+
+fn part1(queue: &PrintQueue) -> Result<usize> {
+    let mut result: usize = 0;
+
+    let mut positional_array: Vec<usize> = vec![usize::MAX; queue.pages.len()];
 
-    for seq in sequences {
-        initialize_positional_array(&seq, &mut positional_array);
-        if verify_ordering(&adj_list, &seq, &mut positional_array) {
-            result += seq[seq.len()/2] as usize;
+    for seq in &queue.sequences {
+        initialize_positional_array(seq, &mut positional_array);
+        if verify_ordering(&queue.adj_list, seq, &mut positional_array) {
+            result += queue.pages.number(seq[seq.len()/2])?;
         }
     }
     Ok(result)
 }
 
-fn swap_elements(ordering: &mut Vec<usize>, pos1: usize, pos2: usize) {
-    let temp = ordering[pos1];
-    ordering[pos1] = ordering[pos2];
-    ordering[pos2] = temp;
-}
-
-/// Ensure the sequence is invalid and reorder it to be valid
-fn verify_invalid_and_reorder(adj_list: &Vec<Vec<usize>>, ordering: &mut Vec<usize>, positional_array: &mut Vec<usize>) -> bool {
-    let mut is_invalid = false;
+/// Orders the pages of an update so that every rule between two of them holds, using Kahn's algorithm on the
+/// subgraph the update's pages induce. Rules involving pages outside the update don't matter. Pages that are free to
+/// go first keep their relative order from the update. `None` if the induced rules contain a cycle, since then no
+/// valid order exists.
+fn topological_order(adj_list: &[Vec<usize>], ordering: &[usize], positional_array: &[usize]) -> Option<Vec<usize>> {
+    // How many rules still require some other page of the update before the page at each position
+    let mut in_degree = vec![0; ordering.len()];
+    for &n in ordering {
+        for &m in &adj_list[n] {
+            if positional_array[m] != usize::MAX {
+                in_degree[positional_array[m]] += 1;
+            }
+        }
+    }
 
-    // By iterating through in reverse order, we will encounter out-of-order elements sooner
-    for i in (0..ordering.len()).rev() {
+    let mut ready: VecDeque<usize> = (0..ordering.len()).filter(|&i| in_degree[i] == 0).collect();
+    let mut sorted = Vec::with_capacity(ordering.len());
+    while let Some(i) = ready.pop_front() {
         let n = ordering[i];
-        // Check each value against its constraints. All values in the adjacency list for this element must have a
-        // position further to the right in the ordering.
+        sorted.push(n);
         for &m in &adj_list[n] {
-            // If any values are -1, they are not in the ordering and we can skip them
-            if positional_array[m] < positional_array[n] {
-                // If the position of the adjacent element is less than the current element, the ordering is invalid
-                is_invalid = true;
-                // Swap the elements
-                swap_elements(ordering, positional_array[m], positional_array[n]);
-                // Update the positions in `positional_array` after swapping
-                let pos_m = positional_array[m];
-                let pos_n = positional_array[n];
-                positional_array[ordering[pos_m]] = pos_m;
-                positional_array[ordering[pos_n]] = pos_n;
+            let j = positional_array[m];
+            if j != usize::MAX {
+                in_degree[j] -= 1;
+                if in_degree[j] == 0 {
+                    ready.push_back(j);
+                }
+            }
+        }
+    }
+
+    (sorted.len() == ordering.len()).then_some(sorted)
+}
+
+//region Cycles
+/// The rules between the given pages only, as an adjacency list over their indices in `pages`.
+fn induced_subgraph(adj_list: &[Vec<usize>], pages: &[usize]) -> Vec<Vec<usize>> {
+    let index: HashMap<usize, usize> = pages.iter().enumerate().map(|(i, &page)| (page, i)).collect();
+    pages.iter()
+        .map(|&page| {
+            adj_list.get(page).map_or(&[][..], Vec::as_slice).iter()
+                .filter_map(|next| index.get(next).copied())
+                .collect()
+        })
+        .collect()
+}
+
+/// Tarjan's algorithm, with an explicit stack so that long chains of rules can't overflow the call stack. Every node
+/// ends up in exactly one component, with its nodes in ascending order.
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; graph.len()];
+    let mut low_link = vec![0; graph.len()];
+    let mut on_stack = vec![false; graph.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_index = 0;
+
+    for root in 0..graph.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // Each frame is a node and how many of its successors were looked at so far
+        let mut frames = vec![(root, 0)];
+        while let Some(&mut (node, ref mut visited)) = frames.last_mut() {
+            if *visited == 0 {
+                index[node] = next_index;
+                low_link[node] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            }
+
+            if let Some(&next) = graph[node].get(*visited) {
+                *visited += 1;
+                if index[next] == UNVISITED {
+                    frames.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(index[next]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if low_link[node] == index[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().expect("the node itself is on the stack");
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
             }
         }
     }
-    is_invalid
+    components
 }
 
-fn part2<R: BufRead>(reader: R) -> Result<usize> {
-    let mut result: usize = 0;
+/// A shortest cycle through the first node of a strongly connected component, found by a breadth-first search that
+/// stays inside the component. `None` if the component is a single node without a rule to itself.
+fn cycle_in_component(graph: &[Vec<usize>], component: &[usize]) -> Option<Vec<usize>> {
+    let start = component[0];
+    let inside: HashSet<usize> = component.iter().copied().collect();
+    let mut came_from: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for &next in &graph[node] {
+            if next == start {
+                let mut cycle = vec![node];
+                while let Some(&previous) = came_from.get(cycle.last().expect("never empty")) {
+                    cycle.push(previous);
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if inside.contains(&next) && !came_from.contains_key(&next) {
+                came_from.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Contradicting rules: `pages[i]` must come before `pages[i + 1]` by the rule on `lines[i]`, and the last page before
+/// the first one again.
+#[derive(Debug, Clone, PartialEq)]
+struct Cycle {
+    /// The labels of the pages
+    pages: Vec<String>,
+    lines: Vec<usize>,
+    /// How many pages are caught up in this and other cycles with it, i.e. the size of the component
+    component_size: usize,
+}
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pages[0])?;
+        for (i, line) in self.lines.iter().enumerate() {
+            write!(f, " → {} (line {})", self.pages[(i + 1) % self.pages.len()], line)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+
+/// One cycle per group of pages whose rules contradict each other, considering only the rules between `pages`. Each
+/// cycle starts at the group's first page in `pages`, and the cycles come in that order too.
+fn find_cycles(queue: &PrintQueue, pages: &[usize]) -> Vec<Cycle> {
+    let graph = induced_subgraph(&queue.adj_list, pages);
+    let mut components = strongly_connected_components(&graph);
+    components.sort_unstable_by_key(|component| component[0]);
+    components.iter()
+        .filter_map(|component| {
+            let cycle = cycle_in_component(&graph, component)?;
+            let cycle: Vec<usize> = cycle.iter().map(|&i| pages[i]).collect();
+            let lines = (0..cycle.len())
+                .map(|i| queue.rule_lines[&(cycle[i], cycle[(i + 1) % cycle.len()])])
+                .collect();
+            let pages = cycle.iter().map(|&page| queue.pages.label(page).to_string()).collect();
+            Some(Cycle { pages, lines, component_size: component.len() })
+        })
+        .collect()
+}
+
+/// Every page that appears in a rule, in the order the pages first appear in the input
+fn rule_pages(queue: &PrintQueue) -> Vec<usize> {
+    let mut pages: Vec<usize> = queue.rule_lines.keys().flat_map(|&(before, after)| [before, after]).collect();
+    pages.sort_unstable();
+    pages.dedup();
+    pages
+}
+
+/// The cycles among all rules, and those among the rules of each update that has any (by index of the update).
+fn analyze_cycles(queue: &PrintQueue) -> (Vec<Cycle>, Vec<(usize, Vec<Cycle>)>) {
+    let global = find_cycles(queue, &rule_pages(queue));
+    let per_update = queue.sequences.iter()
+        .map(|seq| find_cycles(queue, seq))
+        .enumerate()
+        .filter(|(_, cycles)| !cycles.is_empty())
+        .collect();
+    (global, per_update)
+}
+
+fn cycles_table(global: &[Cycle], per_update: &[(usize, Vec<Cycle>)]) -> Table {
+    let mut table = Table::new("Rule cycles", &["scope", "pages involved", "cycle"]);
+    let scoped = global.iter().map(|cycle| ("all rules".to_string(), cycle))
+        .chain(per_update.iter().flat_map(|(i, cycles)| cycles.iter().map(move |cycle| (format!("update {}", i), cycle))));
+    for (scope, cycle) in scoped {
+        table.row([scope, cycle.component_size.to_string(), cycle.to_string()]);
+    }
+    table
+}
+
+fn cycle_json(cycle: &Cycle) -> Json {
+    Json::object([
+        ("pages", Json::Array(cycle.pages.iter().map(|page| Json::string(page.as_str())).collect())),
+        ("lines", Json::Array(cycle.lines.iter().map(Json::number).collect())),
+        ("pages_involved", Json::number(cycle.component_size)),
+    ])
+}
+
+fn cycles_json(global: &[Cycle], per_update: &[(usize, Vec<Cycle>)]) -> Json {
+    Json::object([
+        ("all_rules", Json::Array(global.iter().map(cycle_json).collect())),
+        ("updates", Json::Array(per_update.iter()
+            .map(|(i, cycles)| Json::object([
+                ("update", Json::number(i)),
+                ("cycles", Json::Array(cycles.iter().map(cycle_json).collect())),
+            ]))
+            .collect())),
+    ])
+}
+//endregion
+
+//region Orders
+/// Updates with at most this many pages have their valid orders counted exactly, one subset of pages at a time
+const MAX_EXACT_PAGES: usize = 20;
+
+/// Larger updates have their valid orders enumerated until there are this many
+const ORDER_COUNT_CAP: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OrderCount {
+    Exact(u64),
+    AtLeast(u64),
+}
+
+impl Display for OrderCount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(count) => write!(f, "{}", count),
+            Self::AtLeast(count) => write!(f, "at least {}", count),
+        }
+    }
+}
+
+/// How much freedom the rules leave an update
+#[derive(Debug, Clone, PartialEq)]
+struct OrderAnalysis {
+    /// Only one order is valid: every two neighbours in it are related by a rule, which makes it a Hamiltonian path
+    total: bool,
+    /// How many orders are valid, i.e. linear extensions of the rules between the update's pages. Zero if they
+    /// contain a cycle.
+    valid_orders: OrderCount,
+    /// The page that is in the middle of every valid order, if there is one
+    fixed_middle: Option<usize>,
+}
+
+/// A page can go anywhere between after all pages that must precede it and before all pages that must follow it. So
+/// it is at the same position in every valid order exactly if the rules relate it to every other page.
+fn analyze_order(adj_list: &[Vec<usize>], seq: &[usize]) -> OrderAnalysis {
+    let graph = induced_subgraph(adj_list, seq);
+    let n = seq.len();
+    // In the subgraph, pages and positions are both the indices into `seq`
+    let indices: Vec<usize> = (0..n).collect();
+    let Some(order) = topological_order(&graph, &indices, &indices) else {
+        return OrderAnalysis { total: false, valid_orders: OrderCount::Exact(0), fixed_middle: None };
+    };
+
+    let total = order.windows(2).all(|pair| graph[pair[0]].contains(&pair[1]));
+
+    // Everything that must follow each page, one bit per page, collected backwards through the order
+    let words = n.div_ceil(64);
+    let mut after = vec![vec![0u64; words]; n];
+    for &page in order.iter().rev() {
+        for &next in &graph[page] {
+            let next_bits = after[next].clone();
+            for (word, next_word) in after[page].iter_mut().zip(next_bits) {
+                *word |= next_word;
+            }
+            after[page][next / 64] |= 1 << (next % 64);
+        }
+    }
+    let mut before_count = vec![0; n];
+    for bits in &after {
+        for (page, count) in before_count.iter_mut().enumerate() {
+            *count += (bits[page / 64] >> (page % 64) & 1) as usize;
+        }
+    }
+    let after_count = |page: usize| after[page].iter().map(|word| word.count_ones() as usize).sum::<usize>();
+
+    let middle = n / 2;
+    let fixed_middle = (0..n)
+        .find(|&page| before_count[page] == middle && after_count(page) == n - 1 - middle)
+        .map(|page| seq[page]);
+
+    let valid_orders = if total {
+        OrderCount::Exact(1)
+    } else if n <= MAX_EXACT_PAGES {
+        OrderCount::Exact(count_orders_exactly(&graph))
+    } else {
+        count_orders_capped(&graph, ORDER_COUNT_CAP)
+    };
+
+    OrderAnalysis { total, valid_orders, fixed_middle }
+}
+
+/// Linear extensions of an acyclic graph of at most `MAX_EXACT_PAGES` nodes: the number of ways to order each set of
+/// nodes that may come first, built up one node at a time.
+fn count_orders_exactly(graph: &[Vec<usize>]) -> u64 {
+    let n = graph.len();
+    let mut required = vec![0usize; n];
+    for (page, successors) in graph.iter().enumerate() {
+        for &next in successors {
+            required[next] |= 1 << page;
+        }
+    }
+
+    let mut ways = vec![0u64; 1 << n];
+    ways[0] = 1;
+    for placed in 0..ways.len() {
+        if ways[placed] == 0 {
+            continue;
+        }
+        for (page, &required) in required.iter().enumerate() {
+            if placed & (1 << page) == 0 && required & !placed == 0 {
+                ways[placed | (1 << page)] += ways[placed];
+            }
+        }
+    }
+    ways[ways.len() - 1]
+}
+
+/// Enumerates the linear extensions of an acyclic graph until there are `cap` of them. Every partial order can be
+/// completed, so each step of the search leads to another valid order.
+fn count_orders_capped(graph: &[Vec<usize>], cap: u64) -> OrderCount {
+    let mut in_degree = vec![0; graph.len()];
+    for &next in graph.iter().flatten() {
+        in_degree[next] += 1;
+    }
+
+    fn extend(graph: &[Vec<usize>], in_degree: &mut [usize], placed: &mut [bool], remaining: usize, count: &mut u64, cap: u64) {
+        if remaining == 0 {
+            *count += 1;
+            return;
+        }
+        for page in 0..graph.len() {
+            if placed[page] || in_degree[page] != 0 {
+                continue;
+            }
+            placed[page] = true;
+            graph[page].iter().for_each(|&next| in_degree[next] -= 1);
+            extend(graph, in_degree, placed, remaining - 1, count, cap);
+            graph[page].iter().for_each(|&next| in_degree[next] += 1);
+            placed[page] = false;
+            if *count >= cap {
+                return;
+            }
+        }
+    }
 
-    let (constraints, sequences) = parse_file(reader)?;
+    let mut count = 0;
+    extend(graph, &mut in_degree, &mut vec![false; graph.len()], graph.len(), &mut count, cap);
+    match count >= cap {
+        true => OrderCount::AtLeast(count),
+        false => OrderCount::Exact(count),
+    }
+}
 
-    // find the largest value among the sequences
-    let max_value: usize = find_max_in_all_sequences(&sequences);
-    let mut positional_array: Vec<usize> = vec![usize::MAX; max_value + 1];
+fn orders_table(queue: &PrintQueue, analyses: &[OrderAnalysis]) -> Table {
+    let mut table = Table::new("Update orders", &["#", "pages", "total", "valid orders", "middle page"]);
+    for (i, (seq, analysis)) in queue.sequences.iter().zip(analyses).enumerate() {
+        let middle = match (analysis.fixed_middle, analysis.valid_orders) {
+            (Some(page), _) => queue.pages.label(page).to_string(),
+            (None, OrderCount::Exact(0)) => "-".to_string(),
+            (None, _) => "varies".to_string(),
+        };
+        table.row([i.to_string(), seq.len().to_string(), analysis.total.to_string(), analysis.valid_orders.to_string(), middle]);
+    }
+    table
+}
 
-    let adj_list = parse_adj_list(constraints)?;
+fn orders_json(queue: &PrintQueue, analyses: &[OrderAnalysis]) -> Json {
+    Json::Array(queue.sequences.iter().zip(analyses)
+        .enumerate()
+        .map(|(i, (seq, analysis))| {
+            let (count, capped) = match analysis.valid_orders {
+                OrderCount::Exact(count) => (count, false),
+                OrderCount::AtLeast(count) => (count, true),
+            };
+            Json::object([
+                ("update", Json::number(i)),
+                ("pages", Json::number(seq.len())),
+                ("total", Json::Bool(analysis.total)),
+                ("valid_orders", Json::number(count)),
+                ("capped", Json::Bool(capped)),
+                ("fixed_middle", analysis.fixed_middle.map_or(Json::Null, |page| Json::string(queue.pages.label(page)))),
+            ])
+        })
+        .collect())
+}
+//endregion
+
+//region DOT
+/// What `--dot` exports: every rule, or the rules between the pages of one update
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DotScope {
+    AllRules,
+    Update(usize),
+}
 
-    for mut seq in sequences {
-        initialize_positional_array(&seq, &mut positional_array);
-        if verify_invalid_and_reorder(&adj_list, &mut seq, &mut positional_array) {
-            result += seq[seq.len()/2] as usize;
+impl DotScope {
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "all" => Ok(Self::AllRules),
+            _ => name.parse().map(Self::Update)
+                .map_err(|_| anyhow!("Invalid --dot '{}', expected all or the index of an update", name)),
         }
     }
+}
+
+/// The rules between `pages` as a Graphviz digraph, each edge with the line of its rule as tooltip. With `in_order`,
+/// `pages` is an update: its pages are annotated with their position, and the rules its current order breaks are
+/// drawn in red.
+fn to_dot(queue: &PrintQueue, name: &str, pages: &[usize], in_order: bool) -> String {
+    let graph = induced_subgraph(&queue.adj_list, pages);
+    let mut dot = format!("digraph \"{}\" {{\n", name);
+    // Labels are free-form, so quotes in them need escaping
+    let label = |page: usize| queue.pages.label(page).replace('\\', "\\\\").replace('"', "\\\"");
+
+    for (i, &page) in pages.iter().enumerate() {
+        match in_order {
+            true => dot += &format!("    \"{}\" [label=\"{}\\n#{}\"];\n", label(page), label(page), i),
+            false => dot += &format!("    \"{}\";\n", label(page)),
+        }
+    }
+    for (i, successors) in graph.iter().enumerate() {
+        for &j in successors {
+            let line = queue.rule_lines[&(pages[i], pages[j])];
+            // In an update, a page's index is its position
+            let violated = if in_order && j < i { ", color=red, penwidth=2" } else { "" };
+            dot += &format!("    \"{}\" -> \"{}\" [tooltip=\"line {}\"{}];\n", label(pages[i]), label(pages[j]), line, violated);
+        }
+    }
+
+    dot += "}\n";
+    dot
+}
+
+fn export_dot(queue: &PrintQueue, scope: DotScope) -> Result<String> {
+    match scope {
+        DotScope::AllRules => Ok(to_dot(queue, "rules", &rule_pages(queue), false)),
+        DotScope::Update(i) => {
+            let seq = queue.sequences.get(i)
+                .ok_or_else(|| anyhow!("No update {}, there are only {}", i, queue.sequences.len()))?;
+            Ok(to_dot(queue, &format!("update {}", i), seq, true))
+        }
+    }
+}
+//endregion
+
+fn part2(queue: &PrintQueue) -> Result<usize> {
+    let mut result: usize = 0;
+
+    let mut positional_array: Vec<usize> = vec![usize::MAX; queue.pages.len()];
+
+    for seq in &queue.sequences {
+        initialize_positional_array(seq, &mut positional_array);
+        if verify_ordering(&queue.adj_list, seq, &mut positional_array) {
+            continue;
+        }
+
+        let Some(reordered) = topological_order(&queue.adj_list, seq, &positional_array) else {
+            let cycles = find_cycles(queue, seq);
+            bail!("No valid order for update {}: {}", queue.pages.update(seq), cycles.iter().join("; "));
+        };
+        initialize_positional_array(&reordered, &mut positional_array);
+        if !verify_ordering(&queue.adj_list, &reordered, &mut positional_array) {
+            bail!("Reordering update {} gave {}, which still breaks a rule", queue.pages.update(seq), queue.pages.update(&reordered));
+        }
+        result += queue.pages.number(reordered[reordered.len()/2])?;
+    }
     Ok(result)
 }
 
 fn main() -> Result<()> {
     start_day(DAY);
+    let mut runner = Runner::with_options(DAY, INPUT_FILE, &["--cycles", "--orders", "--dot", "--dot-file"])?;
+    let cycles_format = runner.option("--cycles").map(Format::from_name).transpose()?;
+    let orders_format = runner.option("--orders").map(Format::from_name).transpose()?;
+    let dot_scope = runner.option("--dot").map(DotScope::from_name).transpose()?;
+    let dot_file = runner.option("--dot-file").map(PathBuf::from);
 
-    //region Part 1
-    println!("=== Part 1 ===");
+    //region Parse
+    runner.section("Parse");
+
+    let parsed = runner.parse(|input| parse_print_queue(input.as_bytes()));
+    //endregion
 
-    assert_eq!(143, part1(BufReader::new(TEST.as_bytes()))?);
+    //region Part 1
+    runner.section("Part 1");
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file)?);
-    println!("Result = {}", result);
+    runner.example(143, || part1(&parse_print_queue(TEST.as_bytes())?));
+    runner.solve_expecting(&parsed, 5964, part1);
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part1(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part1(&parse_print_queue(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
     //endregion
 
     //region Part 2
-    println!("\n=== Part 2 ===");
+    runner.section("Part 2");
 
-    assert_eq!(123, part2(BufReader::new(TEST.as_bytes()))?);
-
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part2(input_file)?);
-    println!("Result = {}", result);
+    runner.example(123, || part2(&parse_print_queue(TEST.as_bytes())?));
+    runner.solve_expecting(&parsed, 4719, part2);
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part2(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part2(&parse_print_queue(Input::open(INPUT_FILE_BIG_BOY)?.as_bytes())?));
+    //endregion
+
+    //region Cycles
+    if let Some(format) = cycles_format {
+        runner.section("Cycles");
+
+        match parsed.default_input() {
+            None => println!("No parsed input to analyze"),
+            Some(queue) => {
+                let (global, per_update) = analyze_cycles(queue);
+                match format {
+                    Format::Table => println!("{}", cycles_table(&global, &per_update)),
+                    Format::Json => println!("{}", cycles_json(&global, &per_update)),
+                }
+            }
+        }
+    }
+    //endregion
+
+    //region Orders
+    if let Some(format) = orders_format {
+        runner.section("Orders");
+
+        match parsed.default_input() {
+            None => println!("No parsed input to analyze"),
+            Some(queue) => {
+                let analyses: Vec<OrderAnalysis> = queue.sequences.iter()
+                    .map(|seq| analyze_order(&queue.adj_list, seq))
+                    .collect();
+                match format {
+                    Format::Table => println!("{}", orders_table(queue, &analyses)),
+                    Format::Json => println!("{}", orders_json(queue, &analyses)),
+                }
+            }
+        }
+    }
+    //endregion
+
+    //region DOT
+    if let Some(scope) = dot_scope {
+        runner.section("DOT");
+
+        match parsed.default_input() {
+            None => println!("No parsed input to export"),
+            Some(queue) => {
+                let dot = export_dot(queue, scope)?;
+                match &dot_file {
+                    Some(path) => {
+                        fs::write(path, dot).with_context(|| format!("Failed to write {}", path.display()))?;
+                        println!("Wrote {}", path.display());
+                    }
+                    None => print!("{}", dot),
+                }
+            }
+        }
+    }
     //endregion
 
-    Ok(())
+    runner.finish()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Cursor;
+    use advent_of_code_2024::testing::XorShift;
 
     #[test]
     fn test_empty_input() {
         let input = "";
-        let cursor = Cursor::new(input);
-        let (part1, part2) = parse_file(cursor).unwrap();
+        let (part1, part2, pages) = parse_file(input.as_bytes()).unwrap();
         assert!(part1.is_empty());
         assert!(part2.is_empty());
+        assert_eq!(pages.len(), 0);
     }
 
     #[test]
@@ -257,39 +806,295 @@ mod tests {
         75,47,61
         97,13
         ";
-        let cursor = Cursor::new(input);
-        let (part1, part2) = parse_file(cursor).unwrap();
-        assert_eq!(part1, vec![(47,53), (97,13)]);
-        assert_eq!(part2, vec![vec![75,47,61], vec![97,13]]);
+        let (part1, part2, pages) = parse_file(input.as_bytes()).unwrap();
+        assert_eq!((0..pages.len()).map(|page| pages.label(page)).collect_vec(), vec!["47", "53", "97", "13", "75", "61"]);
+        assert_eq!(part1, vec![Rule { before: 0, after: 1, line: 1 }, Rule { before: 2, after: 3, line: 2 }]);
+        assert_eq!(part2, vec![vec![4,0,5], vec![2,3]]);
+    }
+
+    #[test]
+    fn test_blank_lines_and_crlf() {
+        let input = "\r\n47|53\r\n\r\n47,53\r\n\r\n53,47\r\n";
+        let (rules, sequences, _) = parse_file(input.as_bytes()).unwrap();
+        assert_eq!(rules, vec![Rule { before: 0, after: 1, line: 2 }]);
+        assert_eq!(sequences, vec![vec![0, 1], vec![1, 0]]);
+    }
+
+    #[test]
+    fn test_updates_without_rules() {
+        let (rules, sequences, pages) = parse_file("\n\n75,47\n47\n".as_bytes()).unwrap();
+        assert!(rules.is_empty());
+        assert_eq!(sequences, vec![vec![0, 1], vec![1]]);
+        assert_eq!(pages.len(), 2);
+    }
+
+    #[test]
+    fn test_missing_second_number() {
+        let err = parse_file("47\n\n75,47".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("Missing second number in part1 line"));
     }
 
     #[test]
-    fn test_invalid_number_part1() {
-        let input = "abc|53\n\n75,47";
-        let cursor = Cursor::new(input);
-        let result = parse_file(cursor);
+    fn test_invalid_page_part1() {
+        let input = "a b|53\n\n75,47";
+        let result = parse_file(input.as_bytes());
         assert!(result.is_err());
         let err = format!("{}", result.unwrap_err());
-        assert!(err.contains("Invalid number 'abc'"));
+        assert!(err.contains("Invalid page 'a b'"));
     }
 
     #[test]
-    fn test_invalid_number_part2() {
-        let input = "47|53\n\n75,xyz";
-        let cursor = Cursor::new(input);
-        let result = parse_file(cursor);
+    fn test_invalid_page_part2() {
+        let input = "47|53\n\n75,,47";
+        let result = parse_file(input.as_bytes());
         assert!(result.is_err());
         let err = format!("{}", result.unwrap_err());
-        assert!(err.contains("Invalid number 'xyz'"));
+        assert!(err.contains("Invalid page ''"));
+    }
+
+    #[test]
+    fn test_any_page_labels() {
+        // Far too large to index a table by, and not numbers at all
+        let input = "4000000000|7\n7|cover\nabc|cover\n\ncover,7,4000000000\n4000000000,7,abc\n";
+        let queue = parse_print_queue(input.as_bytes()).unwrap();
+        assert_eq!(queue.pages.len(), 4);
+        assert_eq!(queue.adj_list.len(), 4);
+
+        // Reordering the first update gives 4000000000,7,cover
+        assert_eq!(part1(&queue).unwrap(), 7);
+        assert_eq!(part2(&queue).unwrap(), 7);
+
+        let queue = parse_print_queue("a|b\n\na,b,c\n".as_bytes()).unwrap();
+        let err = part1(&queue).unwrap_err().to_string();
+        assert_eq!(err, "Page 'b' is not a number, so it can't be added up");
+
+        let queue = parse_print_queue("say\"hi\"|b\n\nb,say\"hi\"\n".as_bytes()).unwrap();
+        let dot = export_dot(&queue, DotScope::Update(0)).unwrap();
+        assert!(dot.contains(r#"    "say\"hi\"" [label="say\"hi\"\n#1"];"#), "{}", dot);
+        assert!(dot.contains(r#"    "say\"hi\"" -> "b" [tooltip="line 1", color=red, penwidth=2];"#), "{}", dot);
+
+        // Pages are compared as written
+        let queue = parse_print_queue("47|53\n\n053,47\n".as_bytes()).unwrap();
+        assert_eq!(part2(&queue).unwrap(), 0);
     }
 
     #[test]
     fn test_extra_data_part1_line() {
         let input = "47|53|99\n\n75,47";
-        let cursor = Cursor::new(input);
-        let result = parse_file(cursor);
+        let result = parse_file(input.as_bytes());
         assert!(result.is_err());
         let err = format!("{}", result.unwrap_err());
         assert!(err.contains("Extra data in part1 line"));
     }
+
+    #[test]
+    fn test_example_reorderings() {
+        let queue = parse_print_queue(TEST.as_bytes()).unwrap();
+        let mut positional_array = vec![usize::MAX; queue.pages.len()];
+
+        let reordered: Vec<String> = queue.sequences[3..].iter()
+            .map(|seq| {
+                initialize_positional_array(seq, &mut positional_array);
+                queue.pages.update(&topological_order(&queue.adj_list, seq, &positional_array).unwrap())
+            })
+            .collect();
+        assert_eq!(reordered, vec!["[97, 75, 47, 61, 53]", "[61, 29, 13]", "[97, 75, 47, 29, 13]"]);
+    }
+
+    #[test]
+    fn test_reordering_a_swap_cannot_fix() {
+        // Both updates break more than one rule, and only 1,2,3 satisfies all of them
+        let queue = parse_print_queue("1|2\n2|3\n1|3\n\n3,2,1\n2,3,1\n".as_bytes()).unwrap();
+        assert_eq!(part2(&queue).unwrap(), 2 + 2);
+    }
+
+    #[test]
+    fn test_reordering_satisfies_all_rules() {
+        let mut rng = XorShift::new(0x853c_49e6_748f_ea9b);
+        let mut next = move || rng.next_u64();
+
+        for _ in 0..200 {
+            // Rules drawn from a hidden total order, so every update has at least one valid order
+            let pages = 2 + (next() % 15) as usize;
+            let rank: Vec<usize> = (0..pages).map(|_| (next() % 1000) as usize).collect();
+            let mut rules = Vec::new();
+            for a in 0..pages {
+                for b in 0..pages {
+                    if rank[a] < rank[b] && next() % 3 != 0 {
+                        rules.push((a, b));
+                    }
+                }
+            }
+            let adj_list = parse_adj_list(rules, pages).unwrap();
+            let mut update: Vec<usize> = (0..pages).filter(|_| next() % 4 != 0).collect();
+            update.sort_by_key(|_| next());
+
+            let mut positional_array = vec![usize::MAX; pages];
+            initialize_positional_array(&update, &mut positional_array);
+            let reordered = topological_order(&adj_list, &update, &positional_array).unwrap();
+
+            let mut sorted_pages = reordered.clone();
+            sorted_pages.sort_unstable();
+            let mut expected_pages = update.clone();
+            expected_pages.sort_unstable();
+            assert_eq!(sorted_pages, expected_pages);
+
+            initialize_positional_array(&reordered, &mut positional_array);
+            assert!(verify_ordering(&adj_list, &reordered, &mut positional_array), "{:?} from {:?}", reordered, update);
+        }
+    }
+
+    #[test]
+    fn test_no_valid_order_is_an_error() {
+        let queue = parse_print_queue("47|53\n53|29\n29|47\n\n47,53,29\n".as_bytes()).unwrap();
+        let err = part2(&queue).unwrap_err().to_string();
+        assert_eq!(err, "No valid order for update [47, 53, 29]: 47 → 53 (line 1) → 29 (line 2) → 47 (line 3)");
+
+        // The cycle doesn't matter to an update that leaves out one of its pages
+        let queue = parse_print_queue("47|53\n53|29\n29|47\n\n53,47\n".as_bytes()).unwrap();
+        assert_eq!(part2(&queue).unwrap(), 53);
+    }
+
+    #[test]
+    fn test_example_has_no_cycles() {
+        let queue = parse_print_queue(TEST.as_bytes()).unwrap();
+        assert_eq!(analyze_cycles(&queue), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_cycles_globally_and_per_update() {
+        let input = "47|53\n53|29\n29|47\n47|29\n61|61\n13|61\n\n47,53\n29,47,13\n53,29,47\n61,13\n";
+        let queue = parse_print_queue(input.as_bytes()).unwrap();
+        let (global, per_update) = analyze_cycles(&queue);
+
+        // 47 → 29 → 47 is the shortest cycle, but all three pages contradict each other. 13|61 is in no cycle.
+        let cycles: Vec<String> = global.iter().map(|cycle| format!("{} of {}", cycle, cycle.component_size)).collect();
+        assert_eq!(cycles, vec!["47 → 29 (line 4) → 47 (line 3) of 3", "61 → 61 (line 5) of 1"]);
+
+        let updates: Vec<String> = per_update.iter()
+            .map(|(i, cycles)| format!("{}: {}", i, cycles.iter().map(|cycle| cycle.pages.join(" ")).join("; ")))
+            .collect();
+        assert_eq!(updates, vec!["1: 29 47", "2: 53 29 47", "3: 61"]);
+    }
+
+    /// Whether the order is total, how many valid orders there are, and the label of the fixed middle page
+    fn analyze(input: &str, seq: usize) -> (bool, OrderCount, Option<String>) {
+        let queue = parse_print_queue(input.as_bytes()).unwrap();
+        let analysis = analyze_order(&queue.adj_list, &queue.sequences[seq]);
+        let middle = analysis.fixed_middle.map(|page| queue.pages.label(page).to_string());
+        (analysis.total, analysis.valid_orders, middle)
+    }
+
+    #[test]
+    fn test_example_orders_are_total() {
+        let queue = parse_print_queue(TEST.as_bytes()).unwrap();
+        for seq in &queue.sequences {
+            let analysis = analyze_order(&queue.adj_list, seq);
+            assert!(analysis.total, "{:?}", seq);
+            assert_eq!(analysis.valid_orders, OrderCount::Exact(1));
+            assert!(analysis.fixed_middle.is_some());
+        }
+        let middle = analyze_order(&queue.adj_list, &queue.sequences[1]).fixed_middle.unwrap();
+        assert_eq!(queue.pages.label(middle), "53");
+    }
+
+    #[test]
+    fn test_partial_orders() {
+        // 1 and 2 in either order, then 3, then 4 and 5 in either order
+        let analysis = analyze("1|3\n2|3\n3|4\n3|5\n\n5,4,3,2,1\n", 0);
+        assert_eq!(analysis, (false, OrderCount::Exact(4), Some("3".to_string())));
+
+        // 2 and 3 can swap, and either is the middle page
+        let analysis = analyze("1|2\n1|3\n\n3,2,1\n", 0);
+        assert_eq!(analysis, (false, OrderCount::Exact(2), None));
+
+        // Only some rules are needed for a total order
+        let analysis = analyze("1|2\n2|3\n3|4\n1|4\n\n4,3,2,1\n", 0);
+        assert_eq!(analysis, (true, OrderCount::Exact(1), Some("3".to_string())));
+
+        let analysis = analyze("1|2\n2|1\n\n1,2,3\n", 0);
+        assert_eq!(analysis, (false, OrderCount::Exact(0), None));
+    }
+
+    #[test]
+    fn test_large_updates_are_capped() {
+        let pages: Vec<String> = (1..=25).map(|page| page.to_string()).collect();
+        let update = pages.join(",");
+        let analysis = analyze(&format!("1|2\n\n{}\n", update), 0);
+        assert_eq!(analysis, (false, OrderCount::AtLeast(ORDER_COUNT_CAP), None));
+
+        // A chain, apart from the last two pages that may swap
+        let chain: Vec<String> = (1..24).map(|page| format!("{}|{}", page, page + 1)).chain(["23|25".to_string()]).collect();
+        let analysis = analyze(&format!("{}\n\n{}\n", chain.join("\n"), update), 0);
+        assert_eq!(analysis, (false, OrderCount::Exact(2), Some("13".to_string())));
+    }
+
+    #[test]
+    fn test_order_counts_match_brute_force() {
+        let mut rng = XorShift::new(0xda3e_39cb_94b9_5bdb);
+        let mut next = move || rng.next_u64();
+
+        for _ in 0..100 {
+            // Only rules from lower to higher pages, so there is no cycle
+            let n = 1 + (next() % 7) as usize;
+            let graph: Vec<Vec<usize>> = (0..n)
+                .map(|a| (a + 1..n).filter(|_| next() % 3 == 0).collect())
+                .collect();
+            let brute_force = (0..n).permutations(n)
+                .filter(|order| {
+                    let mut position = vec![0; n];
+                    order.iter().enumerate().for_each(|(i, &page)| position[page] = i);
+                    (0..n).all(|a| graph[a].iter().all(|&b| position[a] < position[b]))
+                })
+                .count() as u64;
+
+            assert_eq!(count_orders_exactly(&graph), brute_force, "{:?}", graph);
+            assert_eq!(count_orders_capped(&graph, u64::MAX), OrderCount::Exact(brute_force));
+            let capped = if brute_force >= 2 { OrderCount::AtLeast(2) } else { OrderCount::Exact(brute_force) };
+            assert_eq!(count_orders_capped(&graph, 2), capped);
+        }
+    }
+
+    #[test]
+    fn test_update_dot() {
+        let queue = parse_print_queue("47|53\n53|29\n13|47\n\n53,47,29\n".as_bytes()).unwrap();
+        let dot = export_dot(&queue, DotScope::Update(0)).unwrap();
+        assert_eq!(dot, "\
+digraph \"update 0\" {
+    \"53\" [label=\"53\\n#0\"];
+    \"47\" [label=\"47\\n#1\"];
+    \"29\" [label=\"29\\n#2\"];
+    \"53\" -> \"29\" [tooltip=\"line 2\"];
+    \"47\" -> \"53\" [tooltip=\"line 1\", color=red, penwidth=2];
+}
+");
+        assert!(export_dot(&queue, DotScope::Update(1)).unwrap_err().to_string().contains("No update 1"));
+    }
+
+    #[test]
+    fn test_all_rules_dot() {
+        let queue = parse_print_queue(TEST.as_bytes()).unwrap();
+        let dot = export_dot(&queue, DotScope::AllRules).unwrap();
+        assert!(dot.starts_with("digraph \"rules\" {\n    \"47\";\n    \"53\";\n    \"97\";\n"));
+        assert_eq!(dot.matches(" -> ").count(), 21);
+        assert!(dot.contains("    \"47\" -> \"53\" [tooltip=\"line 2\"];\n"));
+        assert!(!dot.contains("red"));
+
+        assert_eq!(DotScope::from_name("3").unwrap(), DotScope::Update(3));
+        assert!(DotScope::from_name("first").is_err());
+    }
+
+    #[test]
+    fn test_components_of_a_long_chain() {
+        // Deep enough that a recursive search would overflow the test thread's stack
+        let n = 200_000;
+        let mut graph: Vec<Vec<usize>> = (0..n).map(|i| vec![i + 1]).collect();
+        graph[n - 1] = vec![0];
+        let components = strongly_connected_components(&graph);
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), n);
+
+        graph[n - 1].clear();
+        assert_eq!(strongly_connected_components(&graph).len(), n);
+    }
 }
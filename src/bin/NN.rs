@@ -1,8 +1,5 @@
 use anyhow::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
-use code_timing_macros::time_snippet;
+use std::io::BufRead;
 use const_format::concatcp;
 use advent_of_code_2024::*;
 
@@ -15,51 +12,52 @@ const TEST: &str = r#"
 
 "#; // TODO: Enter test input
 
-fn part1<R: BufRead>(reader: R) -> Result<usize> {
+fn parse<R: BufRead>(reader: R) -> Result<Vec<String>> {
+    // TODO: Parse the puzzle input into something both parts can use
+    Ok(reader.lines().collect::<std::io::Result<_>>()?)
+}
+
+fn part1(lines: &[String]) -> Result<usize> {
     // TODO: Solve Part 1 of the puzzle
-    let answer = reader.lines().flatten().count();
+    let answer = lines.len();
     Ok(answer)
 }
 
-fn part2<R: BufRead>(reader: R) -> Result<usize> {
+#[allow(dead_code)] // TODO: Remove once part 2 is uncommented in main
+fn part2(_lines: &[String]) -> Result<usize> {
     // TODO: Solve Part 2 of the puzzle
     Ok(0)
 }
 
 fn main() -> Result<()> {
     start_day(DAY);
+    let mut runner = Runner::new(DAY, INPUT_FILE)?;
 
-    //region Part 1
-    println!("=== Part 1 ===");
+    //region Parse
+    runner.section("Parse");
 
-    assert_eq!(2, part1(BufReader::new(TEST.as_bytes()))?);
+    let parsed = runner.parse(|input| parse(input.reader()));
+    //endregion
 
-    let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    let result = time_snippet!(part1(input_file)?);
-    println!("Result = {}", result);
+    //region Part 1
+    runner.section("Part 1");
+
+    runner.example(2, || part1(&parse(TEST.as_bytes())?));
+    runner.solve(&parsed, |lines| part1(lines));
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part1(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part1(&parse(Input::open(INPUT_FILE_BIG_BOY)?.reader())?));
     //endregion
 
     //region Part 2
-    // println!("\n=== Part 2 ===");
-    //
-    // assert_eq!(0, part2(BufReader::new(TEST.as_bytes()))?);
+    // runner.section("Part 2");
     //
-    // let input_file = BufReader::new(File::open(INPUT_FILE)?);
-    // let result = time_snippet!(part2(input_file)?);
-    // println!("Result = {}", result);
+    // runner.example(0, || part2(&parse(TEST.as_bytes())?));
+    // runner.solve(&parsed, |lines| part2(lines));
 
     // TODO: Uncomment for big boy result
-    // let result = time_snippet!(part2(
-    //     BufReader::new(File::open(INPUT_FILE_BIG_BOY)?)
-    // )?);
-    // println!("Result (big boy) = {}", result);
+    // runner.part("Result (big boy)", || part2(&parse(Input::open(INPUT_FILE_BIG_BOY)?.reader())?));
     //endregion
 
-    Ok(())
+    runner.finish()
 }
@@ -0,0 +1,314 @@
+use std::result::Result::Ok;
+use anyhow::*;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where bench runs are appended unless overridden by `AOC_HISTORY`.
+pub const DEFAULT_HISTORY_FILE: &str = "history.tsv";
+
+const HEADER: &str = "timestamp\tcommit\tday\tpart\tmachine\truns\tmedian_ns\tmin_ns";
+
+/// One bench measurement of one part.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub commit: String,
+    pub day: String,
+    pub part: String,
+    pub machine: String,
+    pub runs: usize,
+    pub median: Duration,
+    pub min: Duration,
+}
+
+impl Entry {
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                self.timestamp, self.commit, self.day, self.part, self.machine, self.runs,
+                self.median.as_nanos(), self.min.as_nanos())
+    }
+
+    fn from_line(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 8 {
+            bail!("Expected 8 tab-separated fields but found {}", fields.len());
+        }
+        let nanos = |s: &str| -> Result<Duration> {
+            Ok(Duration::from_nanos(s.parse().with_context(|| format!("Invalid duration '{}'", s))?))
+        };
+        Ok(Self {
+            timestamp: fields[0].parse().with_context(|| format!("Invalid timestamp '{}'", fields[0]))?,
+            commit: fields[1].to_string(),
+            day: fields[2].to_string(),
+            part: fields[3].to_string(),
+            machine: fields[4].to_string(),
+            runs: fields[5].parse().with_context(|| format!("Invalid run count '{}'", fields[5]))?,
+            median: nanos(fields[6])?,
+            min: nanos(fields[7])?,
+        })
+    }
+}
+
+/// An append-only TSV file of bench results. No external service needed, and it diffs nicely.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The history file named by `AOC_HISTORY`, or [`DEFAULT_HISTORY_FILE`].
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("AOC_HISTORY").unwrap_or_else(|_| DEFAULT_HISTORY_FILE.to_string()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &Entry) -> Result<()> {
+        let is_new = !self.path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        if is_new {
+            writeln!(file, "{}", HEADER)?;
+        }
+        writeln!(file, "{}", entry.to_line())?;
+        Ok(())
+    }
+
+    /// All entries in the order they were recorded. A missing file is an empty history.
+    pub fn load(&self) -> Result<Vec<Entry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+
+        content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && *line != HEADER)
+            .map(|(i, line)| Entry::from_line(line)
+                .with_context(|| format!("{}:{}", self.path.display(), i + 1)))
+            .collect()
+    }
+}
+
+/// Builds an entry for the current commit and machine.
+pub fn measurement(day: &str, part: &str, samples: &[Duration]) -> Entry {
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+
+    Entry {
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        commit: current_commit(),
+        day: day.to_string(),
+        part: part.to_string(),
+        machine: machine_fingerprint(),
+        runs: sorted.len(),
+        median: sorted.get(sorted.len() / 2).copied().unwrap_or_default(),
+        min: sorted.first().copied().unwrap_or_default(),
+    }
+}
+
+/// Short hash of HEAD, with a `-dirty` suffix if the work tree has uncommitted changes.
+pub fn current_commit() -> String {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(hash) if !hash.is_empty() => {
+            let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|s| !s.is_empty());
+            if dirty { format!("{}-dirty", hash) } else { hash }
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Identifies the machine by OS, architecture, CPU model and count and hostname, so timings from
+/// different machines are never compared with each other.
+pub fn machine_fingerprint() -> String {
+    let cpu_model = fs::read_to_string("/proc/cpuinfo").ok()
+        .and_then(|info| info.lines()
+            .find(|l| l.starts_with("model name"))
+            .and_then(|l| l.split(':').nth(1))
+            .map(|m| m.trim().to_string()))
+        .unwrap_or_default();
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let hostname = fs::read_to_string("/etc/hostname").ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_default();
+
+    let description = format!("{}|{}|{}|{}|{}",
+                              std::env::consts::OS, std::env::consts::ARCH, cpu_model, cpus, hostname.trim());
+    format!("{:016x}", fnv1a(description.as_bytes()))
+}
+
+/// FNV-1a, because the std hashers don't promise to be stable between Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// The timing of one part at one commit. If a commit was benched several times, the fastest median
+/// is kept since slower runs are usually noise from other load on the machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub commit: String,
+    pub median: Duration,
+    /// Slowdown relative to the previous commit, in percent. Negative values are speedups.
+    pub change: Option<f64>,
+    pub regression: bool,
+}
+
+/// The timing trend of each part of `day` on `machine`, parts in the order they were first benched.
+pub fn trends(entries: &[Entry], day: &str, machine: &str, threshold_percent: f64) -> Vec<(String, Vec<Point>)> {
+    let mut result: Vec<(String, Vec<Point>)> = Vec::new();
+
+    for entry in entries.iter().filter(|e| e.day == day && e.machine == machine) {
+        let points = match result.iter_mut().find(|(part, _)| *part == entry.part) {
+            Some((_, points)) => points,
+            None => {
+                result.push((entry.part.clone(), Vec::new()));
+                &mut result.last_mut().unwrap().1
+            }
+        };
+        match points.iter_mut().find(|p| p.commit == entry.commit) {
+            Some(point) => point.median = point.median.min(entry.median),
+            None => points.push(Point { commit: entry.commit.clone(), median: entry.median, change: None, regression: false }),
+        }
+    }
+
+    for (_, points) in result.iter_mut() {
+        for i in 1..points.len() {
+            let previous = points[i - 1].median.as_secs_f64();
+            if previous > 0.0 {
+                let change = (points[i].median.as_secs_f64() / previous - 1.0) * 100.0;
+                points[i].change = Some(change);
+                points[i].regression = change > threshold_percent;
+            }
+        }
+    }
+
+    result
+}
+
+/// One bar per point, scaled between the fastest and slowest timing.
+pub fn sparkline(values: &[Duration]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().min().copied().unwrap_or_default().as_secs_f64();
+    let max = values.iter().max().copied().unwrap_or_default().as_secs_f64();
+    values.iter()
+        .map(|v| {
+            if max <= min {
+                return BARS[0];
+            }
+            let scaled = (v.as_secs_f64() - min) / (max - min) * (BARS.len() - 1) as f64;
+            BARS[scaled.round() as usize]
+        })
+        .collect()
+}
+
+/// Renders the trends of `day` as one sparkline and table per part.
+pub fn render(entries: &[Entry], day: &str, machine: &str, threshold_percent: f64) -> String {
+    let trends = trends(entries, day, machine, threshold_percent);
+    if trends.is_empty() {
+        return format!("No bench history for day {} on this machine ({})\n", day, machine);
+    }
+
+    let mut out = String::new();
+    for (part, points) in trends {
+        let medians: Vec<Duration> = points.iter().map(|p| p.median).collect();
+        let _ = writeln!(out, "{}  {}", part, sparkline(&medians));
+        let _ = writeln!(out, "  {:<16} {:>14} {:>9}", "commit", "median", "change");
+        for point in &points {
+            let change = point.change.map(|c| format!("{:+.1}%", c)).unwrap_or_default();
+            let flag = if point.regression { format!("  <-- more than {}% slower", threshold_percent) } else { String::new() };
+            let row = format!("  {:<16} {:>14} {:>9}{}", point.commit, format!("{:.2?}", point.median), change, flag);
+            let _ = writeln!(out, "{}", row.trim_end());
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(commit: &str, part: &str, millis: u64) -> Entry {
+        Entry {
+            timestamp: 0,
+            commit: commit.to_string(),
+            day: "01".to_string(),
+            part: part.to_string(),
+            machine: "m".to_string(),
+            runs: 1,
+            median: Duration::from_millis(millis),
+            min: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_roundtrip() {
+        let path = std::env::temp_dir().join(format!("aoc-history-test-{}.tsv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let history = History::new(&path);
+
+        let entries = vec![entry("aaa", "Part 1: Result", 3), entry("bbb", "Part 2: Result", 5)];
+        for e in &entries {
+            history.append(e).unwrap();
+        }
+        let loaded = history.load().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, entries);
+    }
+
+    #[test]
+    fn test_trends_flag_regression() {
+        let entries = vec![
+            entry("aaa", "Part 1: Result", 10),
+            entry("aaa", "Part 1: Result", 8),
+            entry("bbb", "Part 1: Result", 8),
+            entry("ccc", "Part 1: Result", 12),
+            entry("ccc", "Part 2: Result", 1),
+        ];
+        let trends = trends(&entries, "01", "m", 10.0);
+
+        assert_eq!(trends.len(), 2);
+        let (part, points) = &trends[0];
+        assert_eq!(part, "Part 1: Result");
+        assert_eq!(points.iter().map(|p| p.commit.as_str()).collect::<Vec<_>>(), vec!["aaa", "bbb", "ccc"]);
+        assert_eq!(points[0].median, Duration::from_millis(8));
+        assert_eq!(points.iter().map(|p| p.regression).collect::<Vec<_>>(), vec![false, false, true]);
+        assert!((points[2].change.unwrap() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trends_ignore_other_machines() {
+        let mut other = entry("aaa", "Part 1: Result", 1);
+        other.machine = "other".to_string();
+        assert!(trends(&[other], "01", "m", 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let values: Vec<Duration> = [1, 8, 4, 1].iter().map(|&ms| Duration::from_millis(ms)).collect();
+        assert_eq!(sparkline(&values), "▁█▄▁");
+        assert_eq!(sparkline(&values[..1]), "▁");
+    }
+}
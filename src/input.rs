@@ -0,0 +1,291 @@
+use std::result::Result::Ok;
+use anyhow::*;
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Puzzle input backed either by a memory-mapped file or by an owned buffer (for test input).
+///
+/// Everything handed out borrows from the underlying bytes, so even multi-gigabyte big boy inputs
+/// are never copied into per-line `String`s.
+pub struct Input {
+    data: Data,
+}
+
+enum Data {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Input {
+    /// Memory-maps the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+        // Mapping a zero-length file fails on some platforms, and there is nothing to map anyway
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::from_bytes(Vec::new()));
+        }
+
+        // SAFETY: Input files are only read. If another process truncates the file while it is
+        // mapped, we may read garbage or fault, which is acceptable for puzzle inputs.
+        let map = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to memory-map {}", path.display()))?;
+
+        Ok(Self { data: Data::Mapped(map) })
+    }
+
+    pub fn from_bytes<B: Into<Vec<u8>>>(bytes: B) -> Self {
+        Self { data: Data::Owned(bytes.into()) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.data {
+            Data::Mapped(map) => map,
+            Data::Owned(vec) => vec,
+        }
+    }
+
+    /// Adapter for the `BufRead`-based part functions. `&[u8]` implements `BufRead` directly, so
+    /// no extra buffer is allocated.
+    pub fn reader(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    /// All lines without their `\n` or `\r\n` terminator, empty lines included.
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        lines(self.as_bytes())
+    }
+
+    /// Groups of lines separated by one or more blank (whitespace-only) lines.
+    pub fn blocks(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        blocks(self.as_bytes())
+    }
+}
+
+/// Splits `bytes` into lines the same way `BufRead::lines` does, but without allocating.
+pub fn lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    // An empty input has no lines, rather than a single empty one
+    let bytes = if bytes.is_empty() { None } else { Some(bytes) };
+
+    bytes
+        .into_iter()
+        .flat_map(|bytes| bytes.split(|&b| b == b'\n'))
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Splits `bytes` into blocks of consecutive non-blank lines. Each block keeps its inner newlines,
+/// but leading and trailing blank lines are dropped.
+pub fn blocks(bytes: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+    let mut rest = bytes;
+
+    std::iter::from_fn(move || {
+        // Skip blank lines before the block
+        loop {
+            if rest.is_empty() {
+                return None;
+            }
+            let line_end = rest.iter().position(|&b| b == b'\n').map_or(rest.len(), |i| i + 1);
+            if !rest[..line_end].trim_ascii().is_empty() {
+                break;
+            }
+            rest = &rest[line_end..];
+        }
+
+        // The block ends right before the next blank line
+        let mut end = 0;
+        while end < rest.len() {
+            let line_end = rest[end..].iter().position(|&b| b == b'\n').map_or(rest.len(), |i| end + i + 1);
+            if rest[end..line_end].trim_ascii().is_empty() {
+                break;
+            }
+            end = line_end;
+        }
+
+        let block = &rest[..end];
+        rest = &rest[end..];
+        Some(block.strip_suffix(b"\n").unwrap_or(block))
+    })
+}
+
+/// Whitespace-separated fields of a line, like `str::split_whitespace`.
+pub fn fields(line: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+    line.split(|b| b.is_ascii_whitespace()).filter(|field| !field.is_empty())
+}
+
+/// Parses an unsigned decimal number straight from bytes. Returns `None` for anything that is not
+/// entirely digits or does not fit into a `usize`.
+pub fn parse_usize(bytes: &[u8]) -> Option<usize> {
+    if bytes.is_empty() {
+        return None;
+    }
+    bytes.iter().try_fold(0usize, |acc, &b| {
+        let digit = b.checked_sub(b'0').filter(|d| *d <= 9)?;
+        acc.checked_mul(10)?.checked_add(digit as usize)
+    })
+}
+
+/// Parses a decimal number with an optional leading `-` straight from bytes. Returns `None` for
+/// anything else or for numbers that do not fit into an `i32`.
+pub fn parse_i32(bytes: &[u8]) -> Option<i32> {
+    let (negative, digits) = match bytes.strip_prefix(b"-") {
+        Some(digits) => (true, digits),
+        None => (false, bytes),
+    };
+    // Widened first, so that `i32::MIN` whose magnitude does not fit into an `i32` still parses
+    let magnitude = i64::try_from(parse_usize(digits)?).ok()?;
+    i32::try_from(if negative { -magnitude } else { magnitude }).ok()
+}
+
+/// The default input of a day, `input/NN.txt`.
+pub fn default_path(day: &str) -> PathBuf {
+    PathBuf::from(format!("input/{}.txt", day))
+}
+
+/// One of several inputs pooled for a day, e.g. `input/05/alice.txt`, together with the answers
+/// recorded for it in `input/05/alice.answers`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInput {
+    pub name: String,
+    pub path: PathBuf,
+    pub answers: Answers,
+}
+
+/// Known answers per section, read from lines like `Part 1: 143`. Blank lines and lines starting
+/// with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Answers(HashMap<String, String>);
+
+impl Answers {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut answers = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (section, answer) = line.split_once(':')
+                .ok_or_else(|| anyhow!("Line {}: expected 'Part N: answer' but found '{}'", i + 1, line))?;
+            answers.insert(section.trim().to_string(), answer.trim().to_string());
+        }
+        Ok(Self(answers))
+    }
+
+    pub fn get(&self, section: &str) -> Option<&str> {
+        self.0.get(section).map(String::as_str)
+    }
+}
+
+/// All inputs in `input/NN/`, sorted by name. A missing directory means no pooled inputs.
+pub fn pooled_inputs(day: &str) -> Result<Vec<NamedInput>> {
+    pooled_inputs_in(Path::new("input").join(day))
+}
+
+fn pooled_inputs_in(dir: PathBuf) -> Result<Vec<NamedInput>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to list {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let answers_path = path.with_extension("answers");
+        let answers = if answers_path.exists() {
+            let text = fs::read_to_string(&answers_path)
+                .with_context(|| format!("Failed to read {}", answers_path.display()))?;
+            Answers::parse(&text).with_context(|| format!("Invalid answers in {}", answers_path.display()))?
+        } else {
+            Answers::default()
+        };
+        inputs.push(NamedInput { name, path, answers });
+    }
+
+    inputs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_lines_matches_bufread() {
+        let text = "3   4\r\n4   3\n\n2   5\n";
+        let expected: Vec<String> = text.as_bytes().lines().map(|l| l.unwrap()).collect();
+        let actual: Vec<&[u8]> = lines(text.as_bytes()).collect();
+        assert_eq!(actual, expected.iter().map(|l| l.as_bytes()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lines_empty_and_unterminated() {
+        assert_eq!(lines(b"").count(), 0);
+        assert_eq!(lines(b"abc").collect::<Vec<_>>(), vec![b"abc"]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let text = "\n47|53\n97|13\n   \n\n75,47,61\n97,13\n";
+        let blocks: Vec<&[u8]> = blocks(text.as_bytes()).collect();
+        assert_eq!(blocks, vec![&b"47|53\n97|13"[..], &b"75,47,61\n97,13"[..]]);
+    }
+
+    #[test]
+    fn test_fields_and_parse() {
+        let parsed: Vec<Option<usize>> = fields(b"  3   42 x7 ").map(parse_usize).collect();
+        assert_eq!(parsed, vec![Some(3), Some(42), None]);
+        assert_eq!(parse_usize(b""), None);
+        assert_eq!(parse_usize(b"99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_parse_i32() {
+        let parsed: Vec<Option<i32>> = fields(b"7 -3 -0 - -x 1-2").map(parse_i32).collect();
+        assert_eq!(parsed, vec![Some(7), Some(-3), Some(0), None, None, None]);
+        assert_eq!(parse_i32(b"-2147483648"), Some(i32::MIN));
+        assert_eq!(parse_i32(b"2147483648"), None);
+        assert_eq!(parse_i32(b"-2147483649"), None);
+    }
+
+    #[test]
+    fn test_answers_parse() {
+        let answers = Answers::parse("# alice\nPart 1: 143\n\nPart 2 :123 \n").unwrap();
+        assert_eq!(answers.get("Part 1"), Some("143"));
+        assert_eq!(answers.get("Part 2"), Some("123"));
+        assert_eq!(answers.get("Part 3"), None);
+        assert!(Answers::parse("143").is_err());
+    }
+
+    #[test]
+    fn test_pooled_inputs() {
+        let dir = std::env::temp_dir().join(format!("aoc-inputs-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bob.txt"), "1\n").unwrap();
+        fs::write(dir.join("alice.txt"), "2\n").unwrap();
+        fs::write(dir.join("alice.answers"), "Part 1: 2\n").unwrap();
+        fs::write(dir.join("notes.md"), "not an input").unwrap();
+
+        let inputs = pooled_inputs_in(dir.clone()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(inputs.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), vec!["alice", "bob"]);
+        assert_eq!(inputs[0].answers.get("Part 1"), Some("2"));
+        assert_eq!(inputs[1].answers, Answers::default());
+        assert!(pooled_inputs_in(PathBuf::from("input/does-not-exist")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_memory_maps_file() {
+        let input = Input::open("input/01.txt").unwrap();
+        let expected = std::fs::read("input/01.txt").unwrap();
+        assert_eq!(input.as_bytes(), &expected[..]);
+        assert_eq!(input.reader().lines().count(), input.lines().count());
+    }
+}
@@ -1,3 +1,15 @@
+pub mod history;
+pub mod input;
+pub mod locations;
+pub mod report;
+pub mod runner;
+pub mod search;
+pub mod testing;
+
+pub use input::Input;
+pub use runner::Runner;
+pub use search::{Match, MultiMatcher, SearchState};
+
 pub fn start_day(day: &str) {
     println!("Advent of Code 2024 - Day {:0>2}", day);
 }
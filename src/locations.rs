@@ -0,0 +1,273 @@
+use anyhow::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use crate::input;
+
+/// Reads whitespace-separated columns of numbers, one row per non-empty line. Unlike the day 01
+/// parser, which only looks at the first two columns, every column is kept and every row must have
+/// the same number of them.
+pub fn parse_columns(bytes: &[u8]) -> Result<Vec<Vec<usize>>> {
+    let mut columns: Vec<Vec<usize>> = Vec::new();
+
+    for (i, line) in input::lines(bytes).enumerate() {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+
+        let row = input::fields(line)
+            .map(|s| input::parse_usize(s)
+                .ok_or_else(|| anyhow!("Line {}: invalid number '{}'", i + 1, String::from_utf8_lossy(s))))
+            .collect::<Result<Vec<_>>>()?;
+
+        if columns.is_empty() {
+            columns = vec![Vec::new(); row.len()];
+        } else if row.len() != columns.len() {
+            bail!("Line {}: expected {} columns but found {}", i + 1, columns.len(), row.len());
+        }
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+
+    Ok(columns)
+}
+
+/// A primitive integer that location IDs can be stored as. Distances and scores are computed in the
+/// same type, so anything that does not fit is reported instead of wrapping.
+pub trait Location: Copy + Ord + Hash + Display {
+    const ZERO: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// `|self - other|`, if it fits into `Self` (for signed types it may not, e.g. `127 - -128`)
+    fn checked_distance(self, other: Self) -> Option<Self>;
+
+    /// `self * count`
+    fn checked_times(self, count: usize) -> Option<Self>;
+}
+
+macro_rules! impl_location {
+    ($($t:ty => $wide:ty),*) => {$(
+        impl Location for $t {
+            const ZERO: Self = 0;
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_distance(self, other: Self) -> Option<Self> {
+                self.max(other).checked_sub(self.min(other))
+            }
+
+            fn checked_times(self, count: usize) -> Option<Self> {
+                // Multiplied in the widest type of the same signedness, because `count` may not fit
+                // into `Self` even when the product does, e.g. `-1i8 * 128` or `0u8 * 300`
+                let count = <$wide>::try_from(count).ok()?;
+                (self as $wide).checked_mul(count).and_then(|product| <$t>::try_from(product).ok())
+            }
+        }
+    )*};
+}
+
+impl_location!(
+    u8 => u128, u16 => u128, u32 => u128, u64 => u128, u128 => u128, usize => u128,
+    i8 => i128, i16 => i128, i32 => i128, i64 => i128, i128 => i128, isize => i128
+);
+
+/// Sum of the distances between the i-th smallest values of both lists (part 1 of day 01), for
+/// lists of any integer type given as iterators. Fails if the lists differ in length or the
+/// distance overflows.
+pub fn checked_total_distance<T, L, R>(left: L, right: R) -> Result<T>
+where
+    T: Location,
+    L: IntoIterator<Item = T>,
+    R: IntoIterator<Item = T>,
+{
+    let mut left: Vec<T> = left.into_iter().collect();
+    let mut right: Vec<T> = right.into_iter().collect();
+    if left.len() != right.len() {
+        bail!("Lists have different lengths ({} and {})", left.len(), right.len());
+    }
+    left.sort_unstable();
+    right.sort_unstable();
+
+    left.iter().zip(&right).try_fold(T::ZERO, |total, (&l, &r)| {
+        l.checked_distance(r)
+            .and_then(|distance| total.checked_add(distance))
+            .ok_or_else(|| anyhow!("Total distance overflows at {} and {}", l, r))
+    })
+}
+
+/// Sum of each left value times how often it appears in the right list (part 2 of day 01), for
+/// lists of any integer type given as iterators. Not symmetric: swapping the lists weighs by the
+/// other list's values. Fails if the score overflows.
+pub fn checked_similarity_score<T, L, R>(left: L, right: R) -> Result<T>
+where
+    T: Location,
+    L: IntoIterator<Item = T>,
+    R: IntoIterator<Item = T>,
+{
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for value in right {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+
+    left.into_iter().try_fold(T::ZERO, |score, value| {
+        let count = counts.get(&value).copied().unwrap_or(0);
+        value.checked_times(count)
+            .and_then(|contribution| score.checked_add(contribution))
+            .ok_or_else(|| anyhow!("Similarity score overflows at {} appearing {} times", value, count))
+    })
+}
+
+/// Pairwise comparison of any number of location lists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation {
+    /// `distances[i][j]` is the total distance between columns `i` and `j`, so it is symmetric
+    pub distances: Vec<Vec<usize>>,
+    /// `similarities[i][j]` is the similarity score of column `i` against column `j`
+    pub similarities: Vec<Vec<usize>>,
+}
+
+/// Compares every pair of columns. Fails if a distance or score overflows; the columns of
+/// [`parse_columns`] always have the same length.
+pub fn reconcile(columns: &[Vec<usize>]) -> Result<Reconciliation> {
+    let n = columns.len();
+    let mut distances = vec![vec![0; n]; n];
+    let mut similarities = vec![vec![0; n]; n];
+
+    for i in 0..n {
+        for j in 0..n {
+            if i < j {
+                distances[i][j] = checked_total_distance(columns[i].iter().copied(), columns[j].iter().copied())
+                    .with_context(|| format!("Columns {} and {}", i + 1, j + 1))?;
+                distances[j][i] = distances[i][j];
+            }
+            similarities[i][j] = checked_similarity_score(columns[i].iter().copied(), columns[j].iter().copied())
+                .with_context(|| format!("Columns {} and {}", i + 1, j + 1))?;
+        }
+    }
+
+    Ok(Reconciliation { distances, similarities })
+}
+
+impl Reconciliation {
+    pub fn columns(&self) -> usize {
+        self.distances.len()
+    }
+
+    /// The two different columns that agree most: the smallest total distance, with ties broken by
+    /// the larger similarity in both directions. `None` with fewer than two columns.
+    pub fn closest_pair(&self) -> Option<(usize, usize)> {
+        let n = self.columns();
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .min_by_key(|&(i, j)| {
+                // Both scores fit into a `usize` on their own, but not necessarily their sum
+                let similarity = self.similarities[i][j] as u128 + self.similarities[j][i] as u128;
+                (self.distances[i][j], std::cmp::Reverse(similarity))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = "3 4 3\n4 3 4\n2 5 2\n1 3 1\n3 9 3\n3 3 3\n";
+
+    #[test]
+    fn test_two_columns_match_day_01() {
+        let columns = parse_columns(TEST.as_bytes()).unwrap();
+        let (left, right) = (columns[0].iter().copied(), columns[1].iter().copied());
+        assert_eq!(checked_total_distance(left.clone(), right.clone()).unwrap(), 11usize);
+        assert_eq!(checked_similarity_score(left, right).unwrap(), 31usize);
+    }
+
+    #[test]
+    fn test_reconcile_three_columns() {
+        let columns = parse_columns(TEST.as_bytes()).unwrap();
+        let reconciliation = reconcile(&columns).unwrap();
+
+        assert_eq!(reconciliation.distances, vec![vec![0, 11, 0], vec![11, 0, 11], vec![0, 11, 0]]);
+        assert_eq!(reconciliation.similarities[0][1], 31);
+        assert_eq!(reconciliation.similarities[1][0], 4 + 3 * 3 * 3);
+        assert_eq!(reconciliation.similarities[0][2], 3 * 3 * 3 + 4 + 2 + 1);
+        assert_eq!(reconciliation.closest_pair(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_checked_matches_usize() {
+        let columns = parse_columns(TEST.as_bytes()).unwrap();
+        let left = columns[0].iter().map(|&v| v as i64);
+        let right = columns[1].iter().map(|&v| v as i64);
+        assert_eq!(checked_total_distance(left.clone(), right.clone()).unwrap(), 11);
+        assert_eq!(checked_similarity_score(left, right).unwrap(), 31);
+    }
+
+    #[test]
+    fn test_checked_signed_and_wide() {
+        assert_eq!(checked_total_distance([-3i128, 5], [4, -1]).unwrap(), 2 + 1);
+        assert_eq!(checked_similarity_score([-3i32, 2, -3], [-3, -3]).unwrap(), -12);
+
+        let big = u128::MAX / 2;
+        assert_eq!(checked_total_distance([0u128, big], [big, 0]).unwrap(), 0);
+        assert_eq!(checked_total_distance([0u128], [big]).unwrap(), big);
+        assert_eq!(checked_similarity_score([big], [big, big]).unwrap(), big * 2);
+    }
+
+    #[test]
+    fn test_checked_overflow_is_an_error() {
+        assert!(checked_total_distance([-128i8], [127]).is_err());
+        assert!(checked_total_distance([0u8, 0], [200, 100]).is_err());
+        assert!(checked_similarity_score([100u8], [100, 100, 100]).is_err());
+        assert!(checked_similarity_score([i64::MIN], [i64::MIN, i64::MIN]).is_err());
+        // More occurrences than the type can count
+        assert!(checked_similarity_score([1u8], vec![1u8; 300]).is_err());
+    }
+
+    #[test]
+    fn test_checked_counts_that_do_not_fit_the_type() {
+        // The count does not fit into the type, but the product does
+        assert_eq!(checked_similarity_score([0u8], vec![0u8; 300]).unwrap(), 0);
+        assert_eq!(checked_similarity_score([-1i8], vec![-1i8; 128]).unwrap(), -128);
+        assert!(checked_similarity_score([-1i8], vec![-1i8; 129]).is_err());
+    }
+
+    #[test]
+    fn test_checked_rejects_different_lengths() {
+        let err = checked_total_distance([1u32, 2], [1u32]).unwrap_err();
+        assert!(err.to_string().contains("different lengths (2 and 1)"));
+    }
+
+    #[test]
+    fn test_closest_pair_needs_two_columns() {
+        assert_eq!(reconcile(&[vec![1, 2]]).unwrap().closest_pair(), None);
+        assert_eq!(reconcile(&[]).unwrap().closest_pair(), None);
+    }
+
+    #[test]
+    fn test_closest_pair_with_huge_similarities() {
+        let reconciliation = Reconciliation {
+            distances: vec![vec![0, 1, 1], vec![1, 0, 1], vec![1, 1, 0]],
+            similarities: vec![vec![0, 1, usize::MAX], vec![1, 0, 0], vec![usize::MAX, 0, 0]],
+        };
+        assert_eq!(reconciliation.closest_pair(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_reconcile_reports_overflow() {
+        let err = reconcile(&[vec![0, 0], vec![usize::MAX, usize::MAX]]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Columns 1 and 2"));
+    }
+
+    #[test]
+    fn test_parse_columns_rejects_ragged_rows() {
+        let err = parse_columns("1 2 3\n4 5\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("Line 2: expected 3 columns but found 2"));
+        assert!(parse_columns("1 x\n".as_bytes()).is_err());
+        assert!(parse_columns("".as_bytes()).unwrap().is_empty());
+    }
+}
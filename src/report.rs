@@ -0,0 +1,178 @@
+use anyhow::*;
+use std::fmt::{self, Display};
+
+/// How diagnostic reports are printed, chosen with options like `--report table|json`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown format '{}', expected table or json", name),
+        }
+    }
+}
+
+/// A plain-text table with right-aligned columns.
+pub struct Table {
+    title: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(title: &str, headers: &[&str]) -> Self {
+        Self {
+            title: title.to_string(),
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row<I, T>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Display,
+    {
+        self.rows.push(cells.into_iter().map(|c| c.to_string()).collect());
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.rows.iter().map(Vec::len).chain([self.headers.len()]).max().unwrap_or(0);
+        let width = |i: usize| {
+            self.rows.iter().chain([&self.headers])
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let widths: Vec<usize> = (0..columns).map(width).collect();
+
+        let line = |f: &mut fmt::Formatter<'_>, cells: &[String]| {
+            let padded: Vec<String> = cells.iter().zip(&widths).map(|(c, w)| format!("{:>w$}", c, w = w)).collect();
+            writeln!(f, "{}", padded.join("  ").trim_end())
+        };
+
+        writeln!(f, "{}", self.title)?;
+        line(f, &self.headers)?;
+        line(f, &widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>())?;
+        for row in &self.rows {
+            line(f, row)?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+/// A minimal JSON value, enough to print reports without pulling in a serialization framework.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Kept as text so any integer type, including `u128` and `i128`, prints exactly
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn number<N: Display>(n: N) -> Self {
+        Self::Number(n.to_string())
+    }
+
+    pub fn string<S: Into<String>>(s: S) -> Self {
+        Self::String(s.into())
+    }
+
+    pub fn object<I, K>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Json)>,
+        K: Into<String>,
+    {
+        Self::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => f.write_str(n),
+            Json::String(s) => write_json_string(f, s),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_alignment() {
+        let mut table = Table::new("Pairs", &["left", "right"]);
+        table.row([1, 100]);
+        table.row(["12345", "2"]);
+        assert_eq!(table.to_string(), "Pairs\n left  right\n-----  -----\n    1    100\n12345      2\n");
+    }
+
+    #[test]
+    fn test_json_display() {
+        let json = Json::object([
+            ("name", Json::string("a \"quoted\"\nline")),
+            ("values", Json::Array(vec![Json::number(1), Json::number(-2i128), Json::Null])),
+            ("ok", Json::Bool(true)),
+        ]);
+        assert_eq!(json.to_string(), r#"{"name":"a \"quoted\"\nline","values":[1,-2,null],"ok":true}"#);
+    }
+
+    #[test]
+    fn test_format_from_name() {
+        assert_eq!(Format::from_name("json").unwrap(), Format::Json);
+        assert!(Format::from_name("xml").is_err());
+    }
+}
@@ -0,0 +1,813 @@
+use std::result::Result::Ok;
+use anyhow::*;
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::history::{self, History};
+use crate::input::{self, Answers, Input, NamedInput};
+
+/// Time budget per part unless overridden by `--timeout <secs>` or `AOC_TIMEOUT`.
+pub const DEFAULT_BUDGET: Duration = Duration::from_secs(60);
+
+/// How often each part is solved by `bench` unless overridden by `--runs`.
+pub const DEFAULT_BENCH_RUNS: usize = 10;
+
+/// How much slower, in percent, a part must get between commits to be flagged by `history`.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 10.0;
+
+/// How a single isolated run ended.
+pub enum Outcome<T> {
+    Solved(T),
+    Failed(Error),
+    Panicked(String),
+    TimedOut,
+}
+
+/// The outcome of a part together with how long it ran. For a timeout, `elapsed` is the time
+/// waited before giving up.
+pub struct PartReport<T> {
+    pub outcome: Outcome<T>,
+    pub elapsed: Duration,
+}
+
+/// Runs `f` on its own thread, catching panics and giving up after `budget`.
+///
+/// A thread that exceeds its budget can't be killed, so it is left running in the background and
+/// dies with the process.
+pub fn run_isolated<T, F>(name: &str, budget: Option<Duration>, f: F) -> PartReport<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let start = Instant::now();
+
+    let spawned = thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            // The receiver is gone if we already timed out, in which case nobody cares anymore
+            let _ = tx.send(result);
+        });
+    if let Err(e) = spawned {
+        return PartReport {
+            outcome: Outcome::Failed(anyhow!("Failed to spawn thread: {}", e)),
+            elapsed: start.elapsed(),
+        };
+    }
+
+    let received = match budget {
+        Some(budget) => rx.recv_timeout(budget),
+        None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    let elapsed = start.elapsed();
+
+    let outcome = match received {
+        Ok(Ok(Ok(value))) => Outcome::Solved(value),
+        Ok(Ok(Err(e))) => Outcome::Failed(e),
+        Ok(Err(payload)) => Outcome::Panicked(panic_message(payload)),
+        Err(RecvTimeoutError::Timeout) => Outcome::TimedOut,
+        Err(RecvTimeoutError::Disconnected) => Outcome::Panicked("Thread exited without a result".to_string()),
+    };
+
+    PartReport { outcome, elapsed }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Panicked with a non-string payload".to_string()
+    }
+}
+
+/// What the day binary was asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    /// Solve every part once
+    Run,
+    /// Solve every part `runs` times and append the timings to the history file
+    Bench { runs: usize },
+    /// Show the recorded timings instead of solving anything
+    History { threshold_percent: f64 },
+}
+
+/// Inputs parsed by [`Runner::parse`], ready to be shared by the parts.
+pub struct Parsed<P> {
+    inputs: Vec<ParsedInput<P>>,
+}
+
+struct ParsedInput<P> {
+    /// `None` for the day's own input file, otherwise the name of the pooled input
+    name: Option<String>,
+    answers: Answers,
+    value: Arc<P>,
+    /// Size of the input file, for the throughput reported by `bench`
+    bytes: usize,
+}
+
+impl<P> Parsed<P> {
+    /// The parsed day's input file, unless it failed to parse or wasn't run.
+    pub fn default_input(&self) -> Option<&P> {
+        self.inputs.iter().find(|i| i.name.is_none()).map(|i| &*i.value)
+    }
+}
+
+fn labeled(label: &str, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{} [{}]", label, name),
+        None => label.to_string(),
+    }
+}
+
+/// Runs the parts of a day with fault isolation. A panic, error or timeout in one part is reported
+/// as that part's result, and the remaining parts still run.
+pub struct Runner {
+    day: String,
+    input_file: PathBuf,
+    budget: Option<Duration>,
+    mode: Mode,
+    history: History,
+    section: Option<String>,
+    failures: Vec<String>,
+    /// Pooled inputs to solve in addition to the default one, see [`Runner::all_inputs`]
+    pooled: Option<Vec<NamedInput>>,
+    /// Per pooled input, the sections it failed in
+    pooled_failures: BTreeMap<String, Vec<String>>,
+    /// Per pooled input, the sections it was solved in without a recorded answer to check against
+    pooled_unverified: BTreeMap<String, Vec<String>>,
+    /// Day-specific `--name value` options, see [`Runner::with_options`]
+    options: HashMap<String, String>,
+}
+
+impl Runner {
+    /// Parses the command line:
+    ///
+    /// ```text
+    /// [run | bench [--runs N] | history [--threshold PERCENT]] [--all-inputs] [--timeout SECS]
+    /// ```
+    ///
+    /// The time budget can also be set with the `AOC_TIMEOUT` environment variable. A budget of 0
+    /// disables the timeout.
+    pub fn new<P: Into<PathBuf>>(day: &str, input_file: P) -> Result<Self> {
+        Self::with_options(day, input_file, &[])
+    }
+
+    /// Like [`Runner::new`], but also accepts the given day-specific options, e.g. `--variant`.
+    /// Each of them takes a value, which the day reads with [`Runner::option`].
+    pub fn with_options<P: Into<PathBuf>>(day: &str, input_file: P, known_options: &[&str]) -> Result<Self> {
+        let mut budget = match std::env::var("AOC_TIMEOUT") {
+            Ok(secs) => parse_budget(&secs)?,
+            Err(_) => Some(DEFAULT_BUDGET),
+        };
+        let mut mode = Mode::Run;
+        let mut all_inputs = false;
+        let mut options = HashMap::new();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{} requires a value", name));
+            match arg.as_str() {
+                "run" => mode = Mode::Run,
+                "bench" => mode = Mode::Bench { runs: DEFAULT_BENCH_RUNS },
+                "history" => mode = Mode::History { threshold_percent: DEFAULT_REGRESSION_THRESHOLD },
+                "--all-inputs" => all_inputs = true,
+                "--timeout" => budget = parse_budget(&value("--timeout")?)?,
+                "--runs" => {
+                    let runs = value("--runs")?;
+                    let runs = runs.parse().ok().filter(|&n| n > 0)
+                        .ok_or_else(|| anyhow!("Invalid run count '{}'", runs))?;
+                    match &mut mode {
+                        Mode::Bench { runs: r } => *r = runs,
+                        _ => bail!("--runs only applies to bench"),
+                    }
+                }
+                "--threshold" => {
+                    let threshold = value("--threshold")?;
+                    let threshold = threshold.trim_end_matches('%').parse()
+                        .with_context(|| format!("Invalid threshold '{}'", threshold))?;
+                    match &mut mode {
+                        Mode::History { threshold_percent } => *threshold_percent = threshold,
+                        _ => bail!("--threshold only applies to history"),
+                    }
+                }
+                option if known_options.contains(&option) => {
+                    options.insert(arg.clone(), value(&arg)?);
+                }
+                _ => bail!("Unknown argument '{}'", arg),
+            }
+        }
+
+        let mut runner = Self::with_mode(day, budget, mode, History::from_env());
+        runner.input_file = input_file.into();
+        runner.options = options;
+        if all_inputs {
+            runner.all_inputs(input::pooled_inputs(day)?);
+        }
+        Ok(runner)
+    }
+
+    pub fn with_budget(day: &str, budget: Option<Duration>) -> Self {
+        Self::with_mode(day, budget, Mode::Run, History::from_env())
+    }
+
+    pub fn with_mode(day: &str, budget: Option<Duration>, mode: Mode, history: History) -> Self {
+        Self {
+            day: day.to_string(),
+            input_file: input::default_path(day),
+            budget,
+            mode,
+            history,
+            section: None,
+            failures: Vec::new(),
+            pooled: None,
+            pooled_failures: BTreeMap::new(),
+            pooled_unverified: BTreeMap::new(),
+            options: HashMap::new(),
+        }
+    }
+
+    /// The value given for a day-specific option on the command line.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Makes [`Runner::solve`] also solve these inputs and check them against their recorded
+    /// answers, e.g. everything in `input/05/` for `--all-inputs`.
+    pub fn all_inputs(&mut self, inputs: Vec<NamedInput>) {
+        self.pooled = Some(inputs);
+    }
+
+    /// Starts a new section of output, e.g. "Part 1". Failures are reported with its name.
+    pub fn section(&mut self, name: &str) {
+        if let Mode::History { .. } = self.mode {
+            return;
+        }
+        if self.section.is_some() {
+            println!();
+        }
+        println!("=== {} ===", name);
+        self.section = Some(name.to_string());
+    }
+
+    fn qualified(&self, label: &str) -> String {
+        match &self.section {
+            Some(section) => format!("{}: {}", section, label),
+            None => label.to_string(),
+        }
+    }
+
+    /// Checks a part against the example from the puzzle text. Examples are never benched.
+    pub fn example<T, F>(&mut self, expected: T, f: F) -> Option<T>
+    where
+        T: Display + Send + 'static,
+        F: Fn() -> Result<T> + Send + 'static,
+    {
+        self.run("Example", Some(expected.to_string()), false, None, f)
+    }
+
+    /// Parses the day's input file, and every pooled input if requested, exactly once. The parse
+    /// time is reported on its own, and the parsed values are shared by all parts via
+    /// [`Runner::solve`]. Inputs that fail to parse are reported and skipped by the parts.
+    pub fn parse<P, F>(&mut self, f: F) -> Parsed<P>
+    where
+        P: Send + Sync + 'static,
+        F: Fn(Input) -> Result<P> + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let mut targets = Vec::new();
+
+        // When pooling, a day without its own default input is fine
+        if self.pooled.is_none() || self.input_file.exists() {
+            targets.push((None, self.input_file.clone(), Answers::default()));
+        }
+        for named in self.pooled.clone().unwrap_or_default() {
+            targets.push((Some(named.name), named.path, named.answers));
+        }
+
+        let mut parsed = Parsed { inputs: Vec::new() };
+        for (name, path, answers) in targets {
+            let f = Arc::clone(&f);
+            let label = labeled("Input", name.as_deref());
+
+            let parse = move || {
+                let input = Input::open(&path)?;
+                let bytes = input.as_bytes().len();
+                Ok((f(input)?, bytes))
+            };
+            match self.measure(&label, true, None, parse) {
+                Some(((value, bytes), summary)) => {
+                    println!("{} parsed ({})", label, summary);
+                    self.pooled_failed(name.as_deref(), false);
+                    parsed.inputs.push(ParsedInput { name, answers, value: Arc::new(value), bytes });
+                }
+                None => self.pooled_failed(name.as_deref(), true),
+            }
+        }
+        parsed
+    }
+
+    /// Solves a part on every parsed input.
+    pub fn solve<P, T, F>(&mut self, parsed: &Parsed<P>, f: F)
+    where
+        P: Send + Sync + 'static,
+        T: Display + Send + 'static,
+        F: Fn(&P) -> Result<T> + Send + Sync + 'static,
+    {
+        self.solve_as(parsed, "Result", None::<T>, f)
+    }
+
+    /// Like [`Runner::solve`], but checks the answer for the day's input file against a previously
+    /// accepted one. Pooled inputs are checked against their own recorded answers.
+    pub fn solve_expecting<P, T, F>(&mut self, parsed: &Parsed<P>, expected: T, f: F)
+    where
+        P: Send + Sync + 'static,
+        T: Display + Send + 'static,
+        F: Fn(&P) -> Result<T> + Send + Sync + 'static,
+    {
+        self.solve_as(parsed, "Result", Some(expected), f)
+    }
+
+    /// The general form of [`Runner::solve`]: reports the results under `label`, e.g. to tell
+    /// alternative implementations of a part apart, and checks the day's input file against
+    /// `expected` if given.
+    pub fn solve_as<P, T, F>(&mut self, parsed: &Parsed<P>, label: &str, expected: Option<T>, f: F)
+    where
+        P: Send + Sync + 'static,
+        T: Display + Send + 'static,
+        F: Fn(&P) -> Result<T> + Send + Sync + 'static,
+    {
+        let expected = expected.map(|e| e.to_string());
+        let f = Arc::new(f);
+        let section = self.section.clone().unwrap_or_default();
+
+        for input in &parsed.inputs {
+            let f = Arc::clone(&f);
+            let value = Arc::clone(&input.value);
+            let expected = match input.name {
+                None => expected.clone(),
+                Some(_) => input.answers.get(&section).map(str::to_string),
+            };
+
+            let label = labeled(label, input.name.as_deref());
+            let verified = expected.is_some();
+            let solved = self.run(&label, expected, true, Some(input.bytes), move || f(&value)).is_some();
+            self.pooled_failed(input.name.as_deref(), !solved);
+            if let (Some(name), true, false) = (&input.name, solved, verified) {
+                let section = self.section.clone().unwrap_or_default();
+                self.pooled_unverified.entry(name.clone()).or_default().push(section);
+            }
+        }
+    }
+
+    /// Like [`Runner::solve_as`], but checks every input, pooled ones without a recorded answer
+    /// included, against `reference` solved on the same parsed value, e.g. the plain implementation
+    /// of a part for one of its variants. The reference is neither printed nor benched.
+    pub fn solve_against<P, T, R, F>(&mut self, parsed: &Parsed<P>, label: &str, reference: R, f: F)
+    where
+        P: Send + Sync + 'static,
+        T: Display + Send + 'static,
+        R: Fn(&P) -> Result<T> + Send + Sync + 'static,
+        F: Fn(&P) -> Result<T> + Send + Sync + 'static,
+    {
+        let reference = Arc::new(reference);
+        let f = Arc::new(f);
+
+        for input in &parsed.inputs {
+            let label = labeled(label, input.name.as_deref());
+            let (reference, value) = (Arc::clone(&reference), Arc::clone(&input.value));
+            let expected = self.measure(&format!("{} reference", label), false, None, move || reference(&value));
+
+            let solved = match expected {
+                Some((expected, _)) => {
+                    let (f, value) = (Arc::clone(&f), Arc::clone(&input.value));
+                    self.run(&label, Some(expected.to_string()), true, Some(input.bytes), move || f(&value)).is_some()
+                }
+                None => false,
+            };
+            self.pooled_failed(input.name.as_deref(), !solved);
+        }
+    }
+
+    /// Keeps track of which pooled inputs failed in which section.
+    fn pooled_failed(&mut self, name: Option<&str>, failed: bool) {
+        if let Some(name) = name {
+            let failures = self.pooled_failures.entry(name.to_string()).or_default();
+            if failed {
+                failures.push(self.section.clone().unwrap_or_default());
+            }
+        }
+    }
+
+    /// Runs a part and prints its result under `label`. Returns the answer if the part finished successfully.
+    pub fn part<T, F>(&mut self, label: &str, f: F) -> Option<T>
+    where
+        T: Display + Send + 'static,
+        F: Fn() -> Result<T> + Send + 'static,
+    {
+        self.run(label, None, true, None, f)
+    }
+
+    /// Runs a part and checks its answer against a previously accepted one.
+    pub fn verify<T, F>(&mut self, label: &str, expected: T, f: F) -> Option<T>
+    where
+        T: Display + Send + 'static,
+        F: Fn() -> Result<T> + Send + 'static,
+    {
+        self.run(label, Some(expected.to_string()), true, None, f)
+    }
+
+    fn run<T, F>(&mut self, label: &str, expected: Option<String>, benchable: bool, bytes: Option<usize>, f: F) -> Option<T>
+    where
+        T: Display + Send + 'static,
+        F: Fn() -> Result<T> + Send + 'static,
+    {
+        let (value, summary) = self.measure(label, benchable, bytes, f)?;
+
+        if let Some(expected) = expected {
+            if value.to_string() != expected {
+                let message = format!("{} answered {} but expected {}", self.qualified(label), value, expected);
+                println!("{}", message);
+                self.failures.push(message);
+                return None;
+            }
+        }
+
+        println!("{} = {} ({})", label, value, summary);
+        Some(value)
+    }
+
+    /// Runs `f` isolated, once or as often as requested by `bench`, and returns its value with a
+    /// summary of the timing, including the throughput over `bytes` of input when benched. Bench
+    /// timings are appended to the history. Failures are reported and yield `None`.
+    fn measure<T, F>(&mut self, label: &str, benchable: bool, bytes: Option<usize>, f: F) -> Option<(T, String)>
+    where
+        T: Send + 'static,
+        F: Fn() -> Result<T> + Send + 'static,
+    {
+        let runs = match self.mode {
+            Mode::History { .. } => return None,
+            Mode::Bench { runs } if benchable => runs,
+            _ => 1,
+        };
+        let name = self.qualified(label);
+        let budget = self.budget.map(|b| b.saturating_mul(runs as u32));
+
+        let report = run_isolated(&name, budget, move || {
+            let mut samples = Vec::with_capacity(runs);
+            let mut value = None;
+            for _ in 0..runs {
+                let start = Instant::now();
+                value = Some(f()?);
+                samples.push(start.elapsed());
+            }
+            Ok((value.expect("at least one run"), samples))
+        });
+
+        let (value, samples) = match report.outcome {
+            Outcome::Solved(solved) => solved,
+            outcome => {
+                self.report_failure(label, outcome, report.elapsed);
+                return None;
+            }
+        };
+
+        if runs == 1 {
+            return Some((value, format!("took {:?}", samples[0])));
+        }
+
+        let entry = history::measurement(&self.day, &name, &samples);
+        if let Err(e) = self.history.append(&entry) {
+            let message = format!("{}: failed to record timing: {:#}", name, e);
+            println!("{}", message);
+            self.failures.push(message);
+        }
+        let mut summary = format!("median {:?}, min {:?} over {} runs", entry.median, entry.min, runs);
+        if let Some(bytes) = bytes {
+            summary += &format!(", {}", throughput(bytes, entry.median));
+        }
+        Some((value, summary))
+    }
+
+    fn report_failure<T>(&mut self, label: &str, outcome: Outcome<T>, elapsed: Duration) {
+        let label = self.qualified(label);
+        let message = match outcome {
+            Outcome::Solved(_) => return,
+            Outcome::Failed(e) => format!("{} failed after {:?}: {:#}", label, elapsed, e),
+            Outcome::Panicked(msg) => format!("{} panicked after {:?}: {}", label, elapsed, msg),
+            Outcome::TimedOut => format!("{} timed out after {:?}", label, elapsed),
+        };
+        println!("{}", message);
+        self.failures.push(message);
+    }
+
+    /// Prints the history if that was requested. Otherwise summarizes the failed parts, if any, as
+    /// the error for `main`.
+    pub fn finish(self) -> Result<()> {
+        if let Mode::History { threshold_percent } = self.mode {
+            let entries = self.history.load()?;
+            print!("{}", history::render(&entries, &self.day, &history::machine_fingerprint(), threshold_percent));
+            return Ok(());
+        }
+        if let Mode::Bench { .. } = self.mode {
+            println!("\nTimings appended to {}", self.history.path().display());
+        }
+        if let Some(pooled) = &self.pooled {
+            println!("\n=== Inputs ===");
+            if pooled.is_empty() {
+                println!("No pooled inputs in input/{}/", self.day);
+            }
+            for (name, failed_sections) in &self.pooled_failures {
+                let unverified_sections = self.pooled_unverified.get(name).filter(|sections| !sections.is_empty());
+                if !failed_sections.is_empty() {
+                    println!("{:<16} FAILED in {}", name, failed_sections.join(", "));
+                } else if let Some(sections) = unverified_sections {
+                    // Solved, but nothing to check the answer against
+                    println!("{:<16} unverified in {}", name, sections.join(", "));
+                } else {
+                    println!("{:<16} ok", name);
+                }
+            }
+        }
+
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        bail!("Day {} had {} failing part(s):\n  {}", self.day, self.failures.len(), self.failures.join("\n  "))
+    }
+}
+
+/// Bytes processed per second, in GB/s.
+fn throughput(bytes: usize, elapsed: Duration) -> String {
+    format!("{:.2} GB/s", bytes as f64 / elapsed.as_secs_f64().max(1e-9) / 1e9)
+}
+
+fn parse_budget(secs: &str) -> Result<Option<Duration>> {
+    let secs: f64 = secs.trim().parse().with_context(|| format!("Invalid timeout '{}'", secs))?;
+    if secs < 0.0 || !secs.is_finite() {
+        bail!("Invalid timeout '{}'", secs);
+    }
+    Ok(if secs == 0.0 { None } else { Some(Duration::from_secs_f64(secs)) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_run_isolated_solved() {
+        let report = run_isolated("test", None, || Ok(42));
+        assert!(matches!(report.outcome, Outcome::Solved(42)));
+    }
+
+    #[test]
+    fn test_run_isolated_error() {
+        let report = run_isolated::<usize, _>("test", None, || Err(anyhow!("bad input")));
+        match report.outcome {
+            Outcome::Failed(e) => assert_eq!(e.to_string(), "bad input"),
+            _ => panic!("Expected a failure"),
+        }
+    }
+
+    #[test]
+    fn test_run_isolated_panic() {
+        let report = run_isolated::<usize, _>("test", None, || {
+            let v: Vec<usize> = Vec::new();
+            Ok(v[3])
+        });
+        match report.outcome {
+            Outcome::Panicked(msg) => assert!(msg.contains("index out of bounds")),
+            _ => panic!("Expected a panic"),
+        }
+    }
+
+    #[test]
+    fn test_run_isolated_timeout() {
+        let budget = Duration::from_millis(20);
+        let report = run_isolated::<usize, _>("test", Some(budget), || loop {
+            thread::sleep(Duration::from_millis(5));
+        });
+        assert!(matches!(report.outcome, Outcome::TimedOut));
+        assert!(report.elapsed >= budget);
+    }
+
+    #[test]
+    fn test_runner_keeps_going_after_failure() {
+        let mut runner = Runner::with_budget("00", Some(Duration::from_secs(5)));
+        runner.section("Part 1");
+        assert_eq!(runner.part::<usize, _>("Result", || panic!("boom")), None);
+        runner.section("Part 2");
+        assert_eq!(runner.example(7, || Ok(7)), Some(7));
+        assert_eq!(runner.verify("Result", 7, || Ok(8)), None);
+
+        let err = runner.finish().unwrap_err().to_string();
+        assert!(err.contains("2 failing part(s)"));
+        assert!(err.contains("Part 1: Result panicked"));
+        assert!(err.contains("Part 2: Result answered 8 but expected 7"));
+    }
+
+    #[test]
+    fn test_bench_records_history() {
+        let path = std::env::temp_dir().join(format!("aoc-runner-test-{}.tsv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut runner = Runner::with_mode("00", None, Mode::Bench { runs: 3 }, History::new(&path));
+
+        runner.section("Part 1");
+        assert_eq!(runner.example(1, || Ok(1)), Some(1));
+        assert_eq!(runner.part("Result", || Ok(2)), Some(2));
+        runner.finish().unwrap();
+
+        let entries = History::new(&path).load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].part, "Part 1: Result");
+        assert_eq!(entries[0].runs, 3);
+    }
+
+    #[test]
+    fn test_solve_reports_failing_pooled_inputs() {
+        let dir = std::env::temp_dir().join(format!("aoc-pooled-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pooled: Vec<NamedInput> = [("alice", "1 2 3", "Part 1: 6"), ("bob", "4 5", "Part 1: 10")]
+            .iter()
+            .map(|(name, content, answers)| {
+                let path = dir.join(format!("{}.txt", name));
+                std::fs::write(&path, content).unwrap();
+                NamedInput { name: name.to_string(), path, answers: input::Answers::parse(answers).unwrap() }
+            })
+            .collect();
+
+        let mut runner = Runner::with_budget("no-such-day", None);
+        runner.all_inputs(pooled);
+        runner.section("Parse");
+        let parsed = runner.parse(|input| {
+            let text = std::str::from_utf8(input.as_bytes())?;
+            text.split_whitespace().map(|n| Ok(n.parse::<usize>()?)).collect::<Result<Vec<usize>>>()
+        });
+        runner.section("Part 1");
+        runner.solve(&parsed, |numbers| Ok(numbers.iter().sum::<usize>()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(runner.pooled_failures.get("alice"), Some(&vec![]));
+        assert_eq!(runner.pooled_failures.get("bob"), Some(&vec!["Part 1".to_string()]));
+        let err = runner.finish().unwrap_err().to_string();
+        assert!(err.contains("Part 1: Result [bob] answered 9 but expected 10"));
+    }
+
+    #[test]
+    fn test_solve_without_answers_is_unverified() {
+        let dir = std::env::temp_dir().join(format!("aoc-pooled-unverified-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pooled: Vec<NamedInput> = [("alice", "1 2 3", "Part 1: 6"), ("bob", "4 5", "")]
+            .iter()
+            .map(|(name, content, answers)| {
+                let path = dir.join(format!("{}.txt", name));
+                std::fs::write(&path, content).unwrap();
+                NamedInput { name: name.to_string(), path, answers: Answers::parse(answers).unwrap() }
+            })
+            .collect();
+
+        let mut runner = Runner::with_budget("no-such-day", None);
+        runner.all_inputs(pooled);
+        runner.section("Parse");
+        let parsed = runner.parse(|input| {
+            let text = std::str::from_utf8(input.as_bytes())?;
+            text.split_whitespace().map(|n| Ok(n.parse::<usize>()?)).collect::<Result<Vec<usize>>>()
+        });
+        runner.section("Part 1");
+        runner.solve(&parsed, |numbers| Ok(numbers.iter().sum::<usize>()));
+        runner.section("Part 2");
+        runner.solve(&parsed, |numbers| Ok(numbers.len()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(runner.pooled_failures.get("bob"), Some(&vec![]));
+        assert_eq!(runner.pooled_unverified.get("alice"), Some(&vec!["Part 2".to_string()]));
+        assert_eq!(runner.pooled_unverified.get("bob"), Some(&vec!["Part 1".to_string(), "Part 2".to_string()]));
+        assert!(runner.finish().is_ok());
+    }
+
+    #[test]
+    fn test_solve_against_checks_unanswered_pooled_inputs() {
+        let dir = std::env::temp_dir().join(format!("aoc-pooled-against-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pooled: Vec<NamedInput> = [("alice", "1 2 3"), ("bob", "4 5")]
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.join(format!("{}.txt", name));
+                std::fs::write(&path, content).unwrap();
+                NamedInput { name: name.to_string(), path, answers: Answers::default() }
+            })
+            .collect();
+
+        let mut runner = Runner::with_budget("no-such-day", None);
+        runner.all_inputs(pooled);
+        runner.section("Parse");
+        let parsed = runner.parse(|input| {
+            let text = std::str::from_utf8(input.as_bytes())?;
+            text.split_whitespace().map(|n| Ok(n.parse::<usize>()?)).collect::<Result<Vec<usize>>>()
+        });
+        runner.section("Part 1");
+        // Wrong for two numbers
+        runner.solve_against(&parsed, "Result (variant)", |numbers| Ok(numbers.iter().sum::<usize>()), |numbers| {
+            Ok(if numbers.len() == 2 { 0 } else { numbers.iter().sum() })
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(runner.pooled_failures.get("alice"), Some(&vec![]));
+        assert_eq!(runner.pooled_failures.get("bob"), Some(&vec!["Part 1".to_string()]));
+        let err = runner.finish().unwrap_err().to_string();
+        assert!(err.contains("Part 1: Result (variant) [bob] answered 0 but expected 9"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_reports_failing_pooled_inputs() {
+        let dir = std::env::temp_dir().join(format!("aoc-pooled-parse-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pooled: Vec<NamedInput> = [("alice", "1 2 3"), ("bob", "4 x")]
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.join(format!("{}.txt", name));
+                std::fs::write(&path, content).unwrap();
+                NamedInput { name: name.to_string(), path, answers: Answers::default() }
+            })
+            .collect();
+
+        let mut runner = Runner::with_budget("no-such-day", None);
+        runner.all_inputs(pooled);
+        runner.section("Parse");
+        let parsed = runner.parse(|input| {
+            let text = std::str::from_utf8(input.as_bytes())?;
+            text.split_whitespace().map(|n| Ok(n.parse::<usize>()?)).collect::<Result<Vec<usize>>>()
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parsed.inputs.len(), 1);
+        assert_eq!(runner.pooled_failures.get("alice"), Some(&vec![]));
+        assert_eq!(runner.pooled_failures.get("bob"), Some(&vec!["Parse".to_string()]));
+        let err = runner.finish().unwrap_err().to_string();
+        assert!(err.contains("Parse: Input [bob] failed"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_once_for_all_parts() {
+        static PARSE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!("aoc-parse-once-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "3 4 5").unwrap();
+        let mut runner = Runner::with_budget("00", None);
+        runner.input_file = path.clone();
+
+        runner.section("Parse");
+        let parsed = runner.parse(|input| {
+            PARSE_CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(input.as_bytes().len())
+        });
+        runner.section("Part 1");
+        runner.solve_expecting(&parsed, 5, |len| Ok(*len));
+        runner.section("Part 2");
+        runner.solve_expecting(&parsed, 10, |len| Ok(len * 2));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(PARSE_CALLS.load(Ordering::SeqCst), 1);
+        assert_eq!(parsed.default_input(), Some(&5));
+        runner.finish().unwrap();
+    }
+
+    #[test]
+    fn test_parse_failure_skips_parts() {
+        let mut runner = Runner::with_budget("00", None);
+        runner.input_file = PathBuf::from("input/does-not-exist.txt");
+
+        let parsed = runner.parse(|input| Ok(input.as_bytes().len()));
+        runner.solve(&parsed, |len| Ok(*len));
+
+        assert!(parsed.default_input().is_none());
+        let err = runner.finish().unwrap_err().to_string();
+        assert!(err.contains("1 failing part(s)"));
+        assert!(err.contains("Input failed"));
+    }
+
+    #[test]
+    fn test_throughput() {
+        assert_eq!(throughput(3_000_000_000, Duration::from_secs(2)), "1.50 GB/s");
+        assert_eq!(throughput(1_000, Duration::from_micros(4)), "0.25 GB/s");
+        assert_eq!(throughput(0, Duration::ZERO), "0.00 GB/s");
+    }
+
+    #[test]
+    fn test_parse_budget() {
+        assert_eq!(parse_budget("0").unwrap(), None);
+        assert_eq!(parse_budget("1.5").unwrap(), Some(Duration::from_millis(1500)));
+        assert!(parse_budget("-1").is_err());
+        assert!(parse_budget("soon").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+
+/// One occurrence of a pattern.
+///
+/// Byte offsets index the haystack's bytes, char offsets count the chars before them. For a `&str`
+/// haystack both always fall on char boundaries, since every pattern is valid UTF-8 itself. For
+/// raw bytes, chars are counted as the bytes that are not UTF-8 continuation bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the pattern in the order the matcher was given them
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// Finds every occurrence of several patterns at once, in a single pass over the haystack
+/// (Aho-Corasick). The automaton is a complete transition table, so each byte costs one lookup no
+/// matter how many patterns there are.
+pub struct MultiMatcher {
+    /// `transitions[state * 256 + byte]` is the next state, state 0 being the root
+    transitions: Vec<u32>,
+    /// The patterns that end in each state, longest first
+    outputs: Vec<Vec<usize>>,
+    /// Length of each pattern in bytes and in chars
+    lengths: Vec<(usize, usize)>,
+    /// The bytes that leave the root state, i.e. the only ones worth stopping at while idle
+    first_bytes: Vec<u8>,
+}
+
+impl MultiMatcher {
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        Self::build(patterns, false)
+    }
+
+    /// Matches ASCII letters regardless of their case. Other chars still have to match exactly.
+    pub fn with_ascii_case_insensitive<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        Self::build(patterns, true)
+    }
+
+    fn build<I, P>(patterns: I, ignore_ascii_case: bool) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let fold = |byte: u8| if ignore_ascii_case { byte.to_ascii_lowercase() } else { byte };
+
+        // The trie of all patterns, with missing edges as `u32::MAX` until the failure links fill them
+        let mut transitions = vec![u32::MAX; 256];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut lengths = Vec::new();
+
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            assert!(!pattern.is_empty(), "Patterns must not be empty");
+            lengths.push((pattern.len(), pattern.chars().count()));
+
+            let mut state = 0;
+            for &byte in pattern.as_bytes() {
+                let byte = fold(byte);
+                let next = transitions[state * 256 + byte as usize];
+                state = if next == u32::MAX {
+                    let new_state = outputs.len();
+                    outputs.push(Vec::new());
+                    transitions.extend(std::iter::repeat_n(u32::MAX, 256));
+                    transitions[state * 256 + byte as usize] = new_state as u32;
+                    new_state
+                } else {
+                    next as usize
+                };
+            }
+            outputs[state].push(i);
+        }
+
+        // Breadth-first, so the failure state of every state is complete before it is needed
+        let mut failure = vec![0usize; outputs.len()];
+        let mut queue = VecDeque::new();
+        for next in &mut transitions[..256] {
+            match *next {
+                u32::MAX => *next = 0,
+                child => queue.push_back(child as usize),
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[failure[state]].clone();
+            outputs[state].extend(inherited);
+
+            for byte in 0..256 {
+                let fallback = transitions[failure[state] * 256 + byte];
+                match transitions[state * 256 + byte] {
+                    u32::MAX => transitions[state * 256 + byte] = fallback,
+                    next => {
+                        failure[next as usize] = fallback as usize;
+                        queue.push_back(next as usize);
+                    }
+                }
+            }
+        }
+        for state_outputs in &mut outputs {
+            state_outputs.sort_by_key(|&pattern| std::cmp::Reverse(lengths[pattern].0));
+        }
+
+        if ignore_ascii_case {
+            for state in 0..outputs.len() {
+                for upper in b'A'..=b'Z' {
+                    transitions[state * 256 + upper as usize] = transitions[state * 256 + upper.to_ascii_lowercase() as usize];
+                }
+            }
+        }
+
+        let first_bytes = (0..=255u8).filter(|&byte| transitions[byte as usize] != 0).collect();
+        Self { transitions, outputs, lengths, first_bytes }
+    }
+
+    /// All occurrences, overlapping ones included, ordered by where they end and then longest first.
+    pub fn find_all<H: AsRef<[u8]> + ?Sized>(&self, haystack: &H) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut state = SearchState::default();
+        for &byte in haystack.as_ref() {
+            self.feed(&mut state, byte, |m| matches.push(m));
+        }
+        matches
+    }
+
+    /// Takes the next byte of a haystack that is fed incrementally, e.g. while reading it in chunks,
+    /// and hands every occurrence ending with it to `on_match`.
+    pub fn feed<F: FnMut(Match)>(&self, state: &mut SearchState, byte: u8, mut on_match: F) {
+        state.node = self.transitions[state.node * 256 + byte as usize] as usize;
+        state.offset += 1;
+        // Continuation bytes belong to the char that started before them
+        if byte & 0xC0 != 0x80 {
+            state.char_offset += 1;
+        }
+
+        for &pattern in &self.outputs[state.node] {
+            let (bytes, chars) = self.lengths[pattern];
+            on_match(Match {
+                pattern,
+                start: state.offset - bytes,
+                end: state.offset,
+                char_start: state.char_offset - chars,
+                char_end: state.char_offset,
+            });
+        }
+    }
+
+    /// Skips ahead to the next byte that can start a match, as long as nothing is partially matched
+    /// yet. Returns how many bytes of `haystack` were skipped, so the next one to `feed` is at that
+    /// index. Where matches are rare, this is much faster than feeding every byte.
+    pub fn skip(&self, state: &mut SearchState, haystack: &[u8]) -> usize {
+        if state.node != 0 {
+            return 0;
+        }
+        let skipped = find_any_byte(&self.first_bytes, haystack).unwrap_or(haystack.len());
+        state.offset += skipped;
+        state.char_offset += haystack[..skipped].iter().filter(|&&byte| byte & 0xC0 != 0x80).count();
+        skipped
+    }
+}
+
+/// Index of the first byte of `haystack` that is one of `needles`.
+///
+/// A few needles are compared 16 bytes at a time with SSE2 on x86_64, or 8 bytes at a time within
+/// a `u64` elsewhere. More needles than that are looked up byte by byte.
+pub fn find_any_byte(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    match needles.len() {
+        0 => None,
+        #[cfg(target_arch = "x86_64")]
+        1..=MAX_WIDE_NEEDLES => find_any_byte_sse2(needles, haystack),
+        #[cfg(not(target_arch = "x86_64"))]
+        1..=MAX_WIDE_NEEDLES => find_any_byte_swar(needles, haystack),
+        _ => find_any_byte_scalar(needles, haystack),
+    }
+}
+
+/// Above this, comparing whole blocks against each needle costs more than a table lookup per byte
+const MAX_WIDE_NEEDLES: usize = 4;
+
+#[cfg(target_arch = "x86_64")]
+fn find_any_byte_sse2(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    use std::arch::x86_64::*;
+
+    let mut i = 0;
+    // SSE2 is part of the x86_64 baseline, and every load is 16 bytes within `haystack`
+    unsafe {
+        let splats: Vec<__m128i> = needles.iter().map(|&needle| _mm_set1_epi8(needle as i8)).collect();
+        while i + 16 <= haystack.len() {
+            let block = _mm_loadu_si128(haystack.as_ptr().add(i) as *const __m128i);
+            let equal = splats.iter().fold(_mm_setzero_si128(), |equal, &splat| {
+                _mm_or_si128(equal, _mm_cmpeq_epi8(block, splat))
+            });
+            let mask = _mm_movemask_epi8(equal);
+            if mask != 0 {
+                return Some(i + mask.trailing_zeros() as usize);
+            }
+            i += 16;
+        }
+    }
+    find_any_byte_swar(needles, &haystack[i..]).map(|found| i + found)
+}
+
+/// Word-at-a-time search: a byte of `word ^ splat(needle)` is zero exactly where the needle is, and
+/// `(x - 0x01..) & !x & 0x80..` flags the lowest zero byte of `x` (higher ones may be flagged wrongly,
+/// but only the lowest matters).
+fn find_any_byte_swar(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const HIGHS: u64 = 0x8080_8080_8080_8080;
+
+    let splats: Vec<u64> = needles.iter().map(|&needle| ONES * needle as u64).collect();
+    let mut words = haystack.chunks_exact(8);
+    for (i, word) in words.by_ref().enumerate() {
+        let word = u64::from_le_bytes(word.try_into().expect("chunks of 8 bytes"));
+        let flags = splats.iter().fold(0, |flags, splat| {
+            let x = word ^ splat;
+            flags | (x.wrapping_sub(ONES) & !x & HIGHS)
+        });
+        if flags != 0 {
+            return Some(i * 8 + flags.trailing_zeros() as usize / 8);
+        }
+    }
+
+    let rest = words.remainder();
+    find_any_byte_scalar(needles, rest).map(|found| haystack.len() - rest.len() + found)
+}
+
+fn find_any_byte_scalar(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    let mut wanted = [false; 256];
+    for &needle in needles {
+        wanted[needle as usize] = true;
+    }
+    haystack.iter().position(|&byte| wanted[byte as usize])
+}
+
+/// Where a [`MultiMatcher`] is in a haystack it is fed incrementally. Start with the default.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SearchState {
+    node: usize,
+    offset: usize,
+    char_offset: usize,
+}
+
+impl SearchState {
+    /// Byte offset of the next byte.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::XorShift;
+
+    fn spans(matches: &[Match]) -> Vec<(usize, usize, usize)> {
+        matches.iter().map(|m| (m.pattern, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn test_finds_overlapping_patterns() {
+        let matcher = MultiMatcher::new(["he", "she", "his", "hers"]);
+        let matches = matcher.find_all("ushers");
+        assert_eq!(spans(&matches), vec![(1, 1, 4), (0, 2, 4), (3, 2, 6)]);
+    }
+
+    #[test]
+    fn test_shared_prefixes() {
+        let matcher = MultiMatcher::new(["do()", "don't()", "do"]);
+        let matches = matcher.find_all("don't()do()");
+        assert_eq!(spans(&matches), vec![(2, 0, 2), (1, 0, 7), (2, 7, 9), (0, 7, 11)]);
+    }
+
+    #[test]
+    fn test_char_offsets() {
+        let haystack = "äö mul(1,2) €do()";
+        let matcher = MultiMatcher::new(["mul(", "do()", "€"]);
+        let matches = matcher.find_all(haystack);
+
+        let found: Vec<(&str, usize, usize)> = matches.iter()
+            .map(|m| (&haystack[m.start..m.end], m.char_start, m.char_end))
+            .collect();
+        assert_eq!(found, vec![("mul(", 3, 7), ("€", 12, 13), ("do()", 13, 17)]);
+        for m in &matches {
+            assert_eq!(haystack[..m.start].chars().count(), m.char_start);
+        }
+    }
+
+    #[test]
+    fn test_multibyte_patterns_need_whole_chars() {
+        // "é" is C3 A9 and "ã" is C3 A3, so they share their first byte
+        let matcher = MultiMatcher::new(["é", "ã"]);
+        assert_eq!(spans(&matcher.find_all("ãé")), vec![(1, 0, 2), (0, 2, 4)]);
+        assert!(matcher.find_all("\u{c3}").is_empty());
+    }
+
+    #[test]
+    fn test_ascii_case_insensitive() {
+        let matcher = MultiMatcher::with_ascii_case_insensitive(["mul(", "Don't()"]);
+        assert_eq!(spans(&matcher.find_all("MUL(1)don'T()")), vec![(0, 0, 4), (1, 6, 13)]);
+        assert!(MultiMatcher::new(["mul("]).find_all("MUL(").is_empty());
+    }
+
+    #[test]
+    fn test_incremental_matches_find_all() {
+        let haystack = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)";
+        let matcher = MultiMatcher::new(["mul(", "don't()"]);
+
+        let mut streamed = Vec::new();
+        let mut state = SearchState::default();
+        for chunk in haystack.as_bytes().chunks(3) {
+            for &byte in chunk {
+                matcher.feed(&mut state, byte, |m| streamed.push(m));
+            }
+        }
+        assert_eq!(streamed, matcher.find_all(haystack));
+        assert_eq!(state.offset(), haystack.len());
+    }
+
+    #[test]
+    fn test_find_any_byte_implementations_agree() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut next = move || rng.next_u64();
+
+        for len in 0..100 {
+            // Few distinct bytes, so needles turn up anywhere from the first byte to not at all
+            let haystack: Vec<u8> = (0..len).map(|_| b'a' + (next() % 24) as u8).collect();
+            for needles in [&b"m"[..], b"md", b"mdx", b"mdxq", b"mdxqa", b"\xff"] {
+                let expected = haystack.iter().position(|byte| needles.contains(byte));
+                assert_eq!(find_any_byte(needles, &haystack), expected, "{:?} in {:?}", needles, haystack);
+                assert_eq!(find_any_byte_swar(needles, &haystack), expected);
+                assert_eq!(find_any_byte_scalar(needles, &haystack), expected);
+            }
+        }
+        assert_eq!(find_any_byte(b"", b"abc"), None);
+    }
+
+    #[test]
+    fn test_skip_only_while_idle() {
+        let haystack = "äöx mul(1,2) mu mul(";
+        let matcher = MultiMatcher::new(["mul("]);
+        let mut state = SearchState::default();
+        let mut matches = Vec::new();
+
+        let bytes = haystack.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            i += matcher.skip(&mut state, &bytes[i..]);
+            if let Some(&byte) = bytes.get(i) {
+                matcher.feed(&mut state, byte, |m| matches.push(m));
+                i += 1;
+            }
+        }
+        assert_eq!(matches, matcher.find_all(haystack));
+        assert_eq!((state.offset, state.char_offset), (bytes.len(), haystack.chars().count()));
+
+        // Halfway through a pattern, nothing may be skipped
+        let mut state = SearchState::default();
+        matcher.feed(&mut state, b'm', |_| {});
+        assert_eq!(matcher.skip(&mut state, b"xyz"), 0);
+    }
+}
@@ -0,0 +1,27 @@
+//! Helpers shared by the tests of the library and of the days. They live outside `#[cfg(test)]`
+//! because the day binaries are tested against the regular build of this library.
+
+/// Xorshift64 generator for randomized tests, so failures are reproducible from the seed. Far too
+/// weak for anything but generating test data.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// A zero seed would only ever produce zeros.
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0, "Xorshift needs a non-zero seed");
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A value in `0..bound`, slightly biased towards the low end unless `bound` is a power of two.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}